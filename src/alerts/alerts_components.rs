@@ -0,0 +1,214 @@
+use crate::world_grid::world_gird_components::GridPosition;
+use bevy::color::palettes::css::{ORANGE, RED, YELLOW};
+use bevy::prelude::*;
+
+///Seconds an alert stays in the feed after it last happened
+const ALERT_LIFETIME: f32 = 12.0;
+///Seconds at the end of its lifetime an alert takes to fade out
+const ALERT_FADE_TIME: f32 = 3.0;
+const MAX_ALERTS: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AlertKind {
+    ///the front item waits at the end of a belt that leads into a building or another belt
+    BeltFull,
+    ///the front item waits at the end of a belt that leads nowhere
+    ConveyorUnlinked,
+    ResourceDepleted,
+}
+
+impl AlertKind {
+    pub fn message(&self) -> &'static str {
+        match self {
+            AlertKind::BeltFull => "Belt is backed up",
+            AlertKind::ConveyorUnlinked => "Conveyor ends nowhere",
+            AlertKind::ResourceDepleted => "Resource node depleted",
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            AlertKind::BeltFull => ORANGE.into(),
+            AlertKind::ConveyorUnlinked => YELLOW.into(),
+            AlertKind::ResourceDepleted => RED.into(),
+        }
+    }
+}
+
+/// A problem somewhere in the factory
+#[derive(Event, Debug, Clone, Copy)]
+pub struct AlertEvent {
+    pub kind: AlertKind,
+    pub grid_position: GridPosition,
+    pub position: Vec3,
+}
+
+#[derive(Debug, Clone)]
+pub struct Alert {
+    pub id: u64,
+    pub kind: AlertKind,
+    pub grid_position: GridPosition,
+    pub position: Vec3,
+    ///how often it happened while it was in the feed
+    pub count: u32,
+    ///seconds since it last happened
+    pub age: f32,
+}
+
+impl Alert {
+    pub fn text(&self) -> String {
+        let mut text = format!(
+            "{} at {},{}",
+            self.kind.message(),
+            self.grid_position.x,
+            self.grid_position.y
+        );
+        if self.count > 1 {
+            text.push_str(&format!(" x{}", self.count));
+        }
+        text
+    }
+
+    pub fn alpha(&self) -> f32 {
+        ((ALERT_LIFETIME - self.age) / ALERT_FADE_TIME).clamp(0.0, 1.0)
+    }
+}
+
+/// Recent alerts, a repeat of the same problem at the same place is counted instead of listed again
+#[derive(Resource, Debug)]
+pub struct AlertFeed {
+    pub alerts: Vec<Alert>,
+    next_id: u64,
+    pub show_markers: bool,
+}
+
+impl Default for AlertFeed {
+    fn default() -> Self {
+        Self {
+            alerts: vec![],
+            next_id: 0,
+            show_markers: true,
+        }
+    }
+}
+
+impl AlertFeed {
+    pub fn push(&mut self, event: &AlertEvent) {
+        if let Some(alert) = self
+            .alerts
+            .iter_mut()
+            .find(|alert| alert.kind == event.kind && alert.grid_position == event.grid_position)
+        {
+            alert.count += 1;
+            alert.age = 0.0;
+            return;
+        }
+        self.alerts.push(Alert {
+            id: self.next_id,
+            kind: event.kind,
+            grid_position: event.grid_position,
+            position: event.position,
+            count: 1,
+            age: 0.0,
+        });
+        self.next_id += 1;
+        if self.alerts.len() > MAX_ALERTS {
+            self.alerts.remove(0);
+        }
+    }
+
+    pub fn advance(&mut self, delta: f32) {
+        for alert in self.alerts.iter_mut() {
+            alert.age += delta;
+        }
+        self.alerts.retain(|alert| alert.age < ALERT_LIFETIME);
+    }
+
+    pub fn get(&self, id: u64) -> Option<&Alert> {
+        self.alerts.iter().find(|alert| alert.id == id)
+    }
+}
+
+#[derive(Component)]
+pub struct AlertFeedRoot;
+
+///Column the alert buttons are spawned into
+#[derive(Component)]
+pub struct AlertList;
+
+#[derive(Component, Debug, Clone, Copy)]
+pub struct AlertButton {
+    pub id: u64,
+}
+
+///Switches the map markers of the alerts on and off
+#[derive(Component)]
+pub struct AlertMarkerToggle;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(kind: AlertKind, x: i32) -> AlertEvent {
+        AlertEvent {
+            kind,
+            grid_position: GridPosition { x, y: 0 },
+            position: Vec3::ZERO,
+        }
+    }
+
+    #[test]
+    fn repeated_alert_is_counted_once() {
+        let mut feed = AlertFeed::default();
+        feed.push(&event(AlertKind::BeltFull, 1));
+        feed.advance(5.0);
+        feed.push(&event(AlertKind::BeltFull, 1));
+
+        assert_eq!(feed.alerts.len(), 1);
+        assert_eq!(feed.alerts[0].count, 2);
+        assert_eq!(feed.alerts[0].age, 0.0);
+        assert!(feed.alerts[0].text().ends_with("x2"));
+    }
+
+    #[test]
+    fn other_place_or_kind_is_a_new_alert() {
+        let mut feed = AlertFeed::default();
+        feed.push(&event(AlertKind::BeltFull, 1));
+        feed.push(&event(AlertKind::BeltFull, 2));
+        feed.push(&event(AlertKind::ConveyorUnlinked, 1));
+
+        assert_eq!(feed.alerts.len(), 3);
+        assert!(feed.alerts.iter().all(|alert| alert.count == 1));
+        let mut ids = feed.alerts.iter().map(|alert| alert.id).collect::<Vec<_>>();
+        ids.dedup();
+        assert_eq!(ids.len(), 3);
+    }
+
+    #[test]
+    fn alerts_fade_and_expire() {
+        let mut feed = AlertFeed::default();
+        feed.push(&event(AlertKind::ResourceDepleted, 1));
+        feed.advance(ALERT_LIFETIME - ALERT_FADE_TIME);
+        assert_eq!(feed.alerts[0].alpha(), 1.0);
+
+        feed.advance(ALERT_FADE_TIME / 2.0);
+        assert!((feed.alerts[0].alpha() - 0.5).abs() < 1e-4);
+
+        feed.advance(ALERT_FADE_TIME);
+        assert!(feed.alerts.is_empty());
+    }
+
+    #[test]
+    fn full_feed_drops_the_oldest_alert() {
+        let mut feed = AlertFeed::default();
+        for x in 0..=MAX_ALERTS as i32 {
+            feed.push(&event(AlertKind::BeltFull, x));
+        }
+
+        assert_eq!(feed.alerts.len(), MAX_ALERTS);
+        assert_eq!(feed.alerts[0].grid_position, GridPosition { x: 1, y: 0 });
+        //the dropped alert comes back as a new one
+        feed.push(&event(AlertKind::BeltFull, 0));
+        assert_eq!(feed.alerts.last().unwrap().count, 1);
+    }
+}
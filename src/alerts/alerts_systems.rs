@@ -0,0 +1,247 @@
+use crate::alerts::alerts_components::*;
+use crate::building::building_components::{BuildingStatus, Lab};
+use crate::building::conveyor_belt::{ConveyorBelt, ConveyorBlockedChanged};
+use crate::general::general_components::GeneralAssets;
+use crate::player::player_components::CameraFocus;
+use crate::world_grid::world_gird_components::WorldGrid;
+use crate::{AppState, MainCamera};
+use bevy::prelude::*;
+use bevy_vector_shapes::prelude::*;
+use std::f32::consts::TAU;
+
+pub fn spawn_alert_feed(
+    mut commands: Commands,
+    camera_query: Query<Entity, With<MainCamera>>,
+    general_assets: Res<GeneralAssets>,
+) {
+    let main_camera = camera_query.get_single().unwrap();
+
+    commands
+        .spawn((
+            TargetCamera(main_camera),
+            Node {
+                position_type: PositionType::Absolute,
+                top: Val::Px(5.0),
+                left: Val::Px(130.0),
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(2.0),
+                padding: UiRect::all(Val::Px(5.0)),
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.1, 0.1, 0.1, 0.8)),
+            BorderRadius::all(Val::Px(5.0)),
+            Visibility::Hidden,
+            AlertFeedRoot,
+            StateScoped(AppState::Game),
+            Name::new("Alert Feed"),
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn((
+                    Button,
+                    Node {
+                        padding: UiRect::all(Val::Px(2.0)),
+                        border: UiRect::all(Val::Px(1.0)),
+                        ..default()
+                    },
+                    BorderColor(Color::BLACK),
+                    BackgroundColor(Color::srgb(0.15, 0.15, 0.15)),
+                    AlertMarkerToggle,
+                ))
+                .with_children(|parent| {
+                    parent.spawn((
+                        Text("Markers".to_owned()),
+                        TextFont {
+                            font: general_assets.default_font.clone(),
+                            font_size: 10.0,
+                            ..default()
+                        },
+                    ));
+                });
+            parent.spawn((
+                Node {
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Val::Px(2.0),
+                    ..default()
+                },
+                AlertList,
+            ));
+        });
+}
+
+///A belt whose front item can't leave it either leads nowhere or into something that is full
+pub fn conveyor_alert_system(
+    mut blocked_events: EventReader<ConveyorBlockedChanged>,
+    world_grid: Res<WorldGrid>,
+    conveyor_q: Query<&ConveyorBelt>,
+    lab_q: Query<(), With<Lab>>,
+    mut alert_event: EventWriter<AlertEvent>,
+) {
+    for event in blocked_events.read().filter(|event| event.blocked) {
+        let Ok(conveyor) = conveyor_q.get(event.conveyor) else {
+            continue;
+        };
+        let Some(last_piece) = conveyor.belt_pieces.last() else {
+            continue;
+        };
+        let kind = match world_grid.get_building_entity(&last_piece.relative_forward_position()) {
+            //a lab takes its items one at a time, the belt waiting in front of it is not full
+            Some(building) if lab_q.contains(building) => continue,
+            Some(_) => AlertKind::BeltFull,
+            None => AlertKind::ConveyorUnlinked,
+        };
+        alert_event.send(AlertEvent {
+            kind,
+            grid_position: last_piece.grid_position,
+            position: world_grid.grid_to_world(&last_piece.grid_position),
+        });
+    }
+}
+
+pub fn building_status_alert_system(
+    world_grid: Res<WorldGrid>,
+    status_q: Query<(&BuildingStatus, &Transform), Changed<BuildingStatus>>,
+    mut alert_event: EventWriter<AlertEvent>,
+) {
+    for (status, transform) in status_q.iter() {
+        if *status != BuildingStatus::NoResource {
+            continue;
+        }
+        alert_event.send(AlertEvent {
+            kind: AlertKind::ResourceDepleted,
+            grid_position: world_grid.grid_position_from_world_position(transform.translation),
+            position: transform.translation,
+        });
+    }
+}
+
+pub fn collect_alerts_system(
+    time: Res<Time>,
+    mut alert_events: EventReader<AlertEvent>,
+    mut feed: ResMut<AlertFeed>,
+) {
+    for event in alert_events.read() {
+        info!(
+            "alert: {} at {:?}",
+            event.kind.message(),
+            event.grid_position
+        );
+        feed.push(event);
+    }
+    feed.advance(time.delta_secs());
+}
+
+pub fn alert_button_system(
+    interaction_query: Query<(&Interaction, &AlertButton), Changed<Interaction>>,
+    marker_toggle_query: Query<&Interaction, (Changed<Interaction>, With<AlertMarkerToggle>)>,
+    mut feed: ResMut<AlertFeed>,
+    mut camera_focus: ResMut<CameraFocus>,
+) {
+    for (interaction, button) in interaction_query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        if let Some(alert) = feed.get(button.id) {
+            camera_focus.target = Some(alert.position);
+        }
+    }
+    for interaction in marker_toggle_query.iter() {
+        if *interaction == Interaction::Pressed {
+            feed.show_markers = !feed.show_markers;
+        }
+    }
+}
+
+///Rebuilds the alert buttons whenever an alert comes, repeats or goes, and fades them out
+pub fn update_alert_feed_system(
+    mut commands: Commands,
+    general_assets: Res<GeneralAssets>,
+    feed: Res<AlertFeed>,
+    list_q: Query<Entity, With<AlertList>>,
+    mut root_q: Query<&mut Visibility, With<AlertFeedRoot>>,
+    button_q: Query<(&AlertButton, &Children)>,
+    mut text_q: Query<&mut TextColor>,
+    mut shown_alerts: Local<(Option<Entity>, Vec<(u64, u32)>)>,
+) {
+    let Ok(list) = list_q.get_single() else {
+        return;
+    };
+    if let Ok(mut visibility) = root_q.get_single_mut() {
+        let new_visibility = if feed.alerts.is_empty() {
+            Visibility::Hidden
+        } else {
+            Visibility::Inherited
+        };
+        visibility.set_if_neq(new_visibility);
+    }
+
+    let shown = (
+        Some(list),
+        feed.alerts
+            .iter()
+            .map(|alert| (alert.id, alert.count))
+            .collect::<Vec<_>>(),
+    );
+    if *shown_alerts != shown {
+        *shown_alerts = shown;
+        commands.entity(list).despawn_descendants();
+        commands.entity(list).with_children(|parent| {
+            //newest first
+            for alert in feed.alerts.iter().rev() {
+                parent
+                    .spawn((
+                        Button,
+                        Node {
+                            width: Val::Px(220.0),
+                            padding: UiRect::all(Val::Px(2.0)),
+                            ..default()
+                        },
+                        BackgroundColor(Color::NONE),
+                        AlertButton { id: alert.id },
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn((
+                            Text(alert.text()),
+                            TextFont {
+                                font: general_assets.default_font.clone(),
+                                font_size: 10.0,
+                                ..default()
+                            },
+                            TextColor(alert.kind.color().with_alpha(alert.alpha())),
+                        ));
+                    });
+            }
+        });
+        return;
+    }
+
+    for (button, children) in button_q.iter() {
+        let Some(alert) = feed.get(button.id) else {
+            continue;
+        };
+        let mut texts = text_q.iter_many_mut(children);
+        while let Some(mut color) = texts.fetch_next() {
+            color.0 = alert.kind.color().with_alpha(alert.alpha());
+        }
+    }
+}
+
+///Pulsing rings on the ground where the alerts happened
+pub fn draw_alert_markers_system(mut painter: ShapePainter, time: Res<Time>, feed: Res<AlertFeed>) {
+    if !feed.show_markers {
+        return;
+    }
+    let pulse = (time.elapsed_secs() * TAU).sin() * 0.5 + 0.5;
+    painter.hollow = true;
+    painter.thickness = 0.05;
+    for alert in feed.alerts.iter() {
+        painter.color = alert.kind.color().with_alpha(alert.alpha());
+        painter.transform = Transform::from_translation(alert.position + Vec3::Y * 0.15)
+            .with_rotation(Quat::from_rotation_x(TAU * 0.25));
+        painter.circle(0.4 + pulse * 0.1);
+    }
+}
+
+pub fn reset_alerts_system(mut feed: ResMut<AlertFeed>) {
+    *feed = AlertFeed::default();
+}
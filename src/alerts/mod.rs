@@ -0,0 +1,36 @@
+use crate::alerts::alerts_components::*;
+use crate::alerts::alerts_systems::*;
+use crate::AppState;
+use bevy::prelude::*;
+
+pub mod alerts_components;
+mod alerts_systems;
+
+pub struct AlertsPlugin;
+
+impl Plugin for AlertsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<AlertEvent>()
+            .init_resource::<AlertFeed>()
+            .add_systems(OnEnter(AppState::Game), spawn_alert_feed)
+            .add_systems(OnExit(AppState::Game), reset_alerts_system)
+            .add_systems(
+                Update,
+                (
+                    building_status_alert_system,
+                    alert_button_system,
+                    update_alert_feed_system.after(alert_button_system),
+                    draw_alert_markers_system,
+                )
+                    .run_if(in_state(AppState::Game)),
+            )
+            .add_systems(
+                PostUpdate,
+                (
+                    conveyor_alert_system,
+                    collect_alerts_system.after(conveyor_alert_system),
+                )
+                    .run_if(in_state(AppState::Game)),
+            );
+    }
+}
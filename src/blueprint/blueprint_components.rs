@@ -0,0 +1,275 @@
+use crate::building::building_components::BuildingType;
+use crate::world_grid::world_gird_components::{GridPosition, GridRotation};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+const BLUEPRINT_TEXT_PREFIX: &str = "bp2";
+///texts shared before the building definitions used short codes for the buildings
+const LEGACY_BLUEPRINT_TEXT_PREFIX: &str = "bp1";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct BuildingSettings {
+    pub active: bool,
+}
+
+impl Default for BuildingSettings {
+    fn default() -> Self {
+        Self { active: true }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BlueprintEntry {
+    pub building_type: BuildingType,
+    ///position relative to the lowest corner of the blueprint
+    pub grid_position: GridPosition,
+    pub grid_rotation: GridRotation,
+    #[serde(default)]
+    pub settings: BuildingSettings,
+}
+
+/// Buildings of a captured region that can be placed again somewhere else
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Blueprint {
+    pub name: String,
+    pub entries: Vec<BlueprintEntry>,
+}
+
+impl Blueprint {
+    ///The blueprint turned a quarter around its corner, shifted back so no position is negative
+    pub fn rotated(&self) -> Blueprint {
+        let mut entries = self
+            .entries
+            .iter()
+            .map(|entry| BlueprintEntry {
+                grid_position: GridPosition {
+                    x: -entry.grid_position.y,
+                    y: entry.grid_position.x,
+                },
+                grid_rotation: entry.grid_rotation.rotated_quarter(),
+                ..entry.clone()
+            })
+            .collect::<Vec<_>>();
+        let min_x = entries.iter().map(|e| e.grid_position.x).min().unwrap_or(0);
+        let min_y = entries.iter().map(|e| e.grid_position.y).min().unwrap_or(0);
+        for entry in entries.iter_mut() {
+            entry.grid_position.x -= min_x;
+            entry.grid_position.y -= min_y;
+        }
+        Blueprint {
+            name: self.name.clone(),
+            entries,
+        }
+    }
+
+    ///Compact text to share the blueprint, `bp2:name:building id,x,y,rotation,active;...`
+    pub fn to_text(&self) -> String {
+        let name = self.name.replace([':', ';'], " ");
+        let entries = self
+            .entries
+            .iter()
+            .filter(|entry| !entry.building_type.id().is_empty())
+            .map(|entry| {
+                format!(
+                    "{},{},{},{},{}",
+                    entry.building_type.id(),
+                    entry.grid_position.x,
+                    entry.grid_position.y,
+                    rotation_code(entry.grid_rotation),
+                    entry.settings.active as u8
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(";");
+        format!("{}:{}:{}", BLUEPRINT_TEXT_PREFIX, name, entries)
+    }
+
+    pub fn from_text(text: &str) -> Result<Blueprint, String> {
+        let mut parts = text.trim().splitn(3, ':');
+        let building_from_text = match parts.next() {
+            Some(BLUEPRINT_TEXT_PREFIX) => building_from_id,
+            Some(LEGACY_BLUEPRINT_TEXT_PREFIX) => building_from_code,
+            _ => return Err("the text is not a blueprint".to_string()),
+        };
+        let name = parts.next().unwrap_or_default().to_string();
+        let mut entries = vec![];
+        for entry in parts.next().unwrap_or_default().split(';') {
+            if entry.is_empty() {
+                continue;
+            }
+            let values = entry.split(',').collect::<Vec<_>>();
+            let [building, x, y, rotation, active] = values[..] else {
+                return Err(format!("malformed blueprint entry {}", entry));
+            };
+            let invalid = || format!("invalid blueprint entry {}", entry);
+            entries.push(BlueprintEntry {
+                building_type: building_from_text(building).ok_or_else(invalid)?,
+                grid_position: GridPosition {
+                    x: x.parse().map_err(|_| invalid())?,
+                    y: y.parse().map_err(|_| invalid())?,
+                },
+                grid_rotation: rotation_from_code(rotation).ok_or_else(invalid)?,
+                settings: BuildingSettings {
+                    active: active != "0",
+                },
+            });
+        }
+        Ok(Blueprint { name, entries })
+    }
+}
+
+fn building_from_id(id: &str) -> Option<BuildingType> {
+    Some(id).filter(|id| !id.is_empty()).map(BuildingType::new)
+}
+
+fn building_from_code(code: &str) -> Option<BuildingType> {
+    let id = match code {
+        "ex" => "extractor",
+        "cb" => "conveyor_belt",
+        "fb" => "fast_conveyor_belt",
+        "xb" => "express_conveyor_belt",
+        "in" => "inserter",
+        "lb" => "lab",
+        _ => return None,
+    };
+    Some(BuildingType::new(id))
+}
+
+fn rotation_code(grid_rotation: GridRotation) -> &'static str {
+    match grid_rotation {
+        GridRotation::N => "n",
+        GridRotation::S => "s",
+        GridRotation::W => "w",
+        GridRotation::E => "e",
+    }
+}
+
+fn rotation_from_code(code: &str) -> Option<GridRotation> {
+    match code {
+        "n" => Some(GridRotation::N),
+        "s" => Some(GridRotation::S),
+        "w" => Some(GridRotation::W),
+        "e" => Some(GridRotation::E),
+        _ => None,
+    }
+}
+
+/// Blueprints kept in `blueprints.ron` next to the save slots
+#[derive(Resource, Serialize, Deserialize, Default)]
+pub struct BlueprintLibrary {
+    pub blueprints: Vec<Blueprint>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq)]
+pub enum BlueprintMode {
+    #[default]
+    Idle,
+    Selecting {
+        start: Option<GridPosition>,
+    },
+    Pasting,
+}
+
+/// System clipboard to share blueprints as text, missing where there is none
+pub struct BlueprintClipboard(pub Option<arboard::Clipboard>);
+
+#[derive(Resource, Default, Debug)]
+pub struct BlueprintTool {
+    pub mode: BlueprintMode,
+    pub current: Option<Blueprint>,
+    ///next blueprint of the library to pick
+    pub library_index: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: &str, x: i32, y: i32, grid_rotation: GridRotation) -> BlueprintEntry {
+        BlueprintEntry {
+            building_type: BuildingType::new(id),
+            grid_position: GridPosition { x, y },
+            grid_rotation,
+            settings: BuildingSettings::default(),
+        }
+    }
+
+    fn blueprint() -> Blueprint {
+        let mut switched_off = entry("lab", 2, 0, GridRotation::S);
+        switched_off.settings.active = false;
+        Blueprint {
+            name: "smelter line".to_string(),
+            entries: vec![
+                entry("extractor", 0, 0, GridRotation::N),
+                entry("conveyor_belt", 0, 1, GridRotation::W),
+                entry("fast_conveyor_belt", 1, 1, GridRotation::E),
+                switched_off,
+            ],
+        }
+    }
+
+    #[test]
+    fn text_round_trip() {
+        let blueprint = blueprint();
+        let text = blueprint.to_text();
+        assert!(text.starts_with("bp2:smelter line:extractor,0,0,n,1;"));
+
+        let parsed = Blueprint::from_text(&text).unwrap();
+        assert_eq!(parsed.name, blueprint.name);
+        assert_eq!(parsed.entries, blueprint.entries);
+    }
+
+    #[test]
+    fn separators_in_the_name_do_not_break_the_text() {
+        let mut blueprint = blueprint();
+        blueprint.name = "a:b;c".to_string();
+        let parsed = Blueprint::from_text(&blueprint.to_text()).unwrap();
+        assert_eq!(parsed.name, "a b c");
+        assert_eq!(parsed.entries, blueprint.entries);
+    }
+
+    #[test]
+    fn invalid_text_is_rejected() {
+        assert!(Blueprint::from_text("hello").is_err());
+        assert!(Blueprint::from_text("bp2:name:extractor,0,0,n").is_err());
+        assert!(Blueprint::from_text("bp2:name:extractor,x,0,n,1").is_err());
+        assert!(Blueprint::from_text("bp2:name:extractor,0,0,q,1").is_err());
+        assert!(Blueprint::from_text("bp2:name:,0,0,n,1").is_err());
+        assert!(Blueprint::from_text("bp2:empty:")
+            .unwrap()
+            .entries
+            .is_empty());
+    }
+
+    #[test]
+    fn legacy_text_codes_are_read() {
+        let parsed =
+            Blueprint::from_text("bp1:smelter line:ex,0,0,n,1;cb,0,1,w,1;fb,1,1,e,1;lb,2,0,s,0")
+                .unwrap();
+        assert_eq!(parsed.entries, blueprint().entries);
+        assert!(Blueprint::from_text("bp1:name:extractor,0,0,n,1").is_err());
+    }
+
+    #[test]
+    fn rotated_keeps_positions_positive() {
+        let rotated = blueprint().rotated();
+        let positions = rotated
+            .entries
+            .iter()
+            .map(|entry| (entry.grid_position.x, entry.grid_position.y))
+            .collect::<Vec<_>>();
+        assert_eq!(positions, vec![(1, 0), (0, 0), (0, 1), (1, 2)]);
+        assert_eq!(rotated.entries[0].grid_rotation, GridRotation::E);
+        assert_eq!(
+            rotated.entries[0].building_type,
+            BuildingType::new("extractor")
+        );
+    }
+
+    #[test]
+    fn four_rotations_restore_the_blueprint() {
+        let blueprint = blueprint();
+        let rotated = blueprint.rotated().rotated().rotated().rotated();
+        assert_eq!(rotated.entries, blueprint.entries);
+    }
+}
@@ -0,0 +1,330 @@
+use crate::blueprint::blueprint_components::*;
+use crate::building::building_components::*;
+use crate::building::building_definitions::BuildingDefinitions;
+use crate::building::building_systems::{can_place_building, spawn_building_on_grid};
+use crate::player::player_components::{GameCursor, Inventory};
+use crate::research::research_components::{Research, TechTree, TechTreeHandle};
+use crate::statistics::statistics_components::ItemConsumedEvent;
+use crate::world_grid::world_gird_components::*;
+use bevy::prelude::*;
+use bevy_persistent::Persistent;
+use bevy_vector_shapes::prelude::*;
+use std::f32::consts::TAU;
+
+pub fn blueprint_input_system(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut game_cursor: ResMut<GameCursor>,
+    mut blueprint_tool: ResMut<BlueprintTool>,
+    mut library: ResMut<Persistent<BlueprintLibrary>>,
+    mut clipboard: NonSendMut<BlueprintClipboard>,
+) {
+    //picking a building from the menu leaves the blueprint tool
+    if blueprint_tool.mode != BlueprintMode::Idle && game_cursor.preview_entity.is_some() {
+        blueprint_tool.mode = BlueprintMode::Idle;
+    }
+    if keys.just_pressed(KeyCode::Escape) {
+        blueprint_tool.mode = BlueprintMode::Idle;
+    }
+    if blueprint_tool.mode == BlueprintMode::Pasting && keys.just_pressed(KeyCode::KeyR) {
+        blueprint_tool.current = blueprint_tool.current.as_ref().map(|b| b.rotated());
+    }
+
+    if !keys.pressed(KeyCode::ControlLeft) && !keys.pressed(KeyCode::ControlRight) {
+        return;
+    }
+    let mut start_tool = |mode: BlueprintMode, blueprint_tool: &mut BlueprintTool| {
+        if let Some(preview_entity) = game_cursor.preview_entity.take() {
+            commands.entity(preview_entity).despawn_recursive();
+        }
+        blueprint_tool.mode = mode;
+    };
+
+    if keys.just_pressed(KeyCode::KeyC) {
+        start_tool(
+            BlueprintMode::Selecting { start: None },
+            &mut blueprint_tool,
+        );
+    }
+    if keys.just_pressed(KeyCode::KeyV) && blueprint_tool.current.is_some() {
+        start_tool(BlueprintMode::Pasting, &mut blueprint_tool);
+    }
+    if keys.just_pressed(KeyCode::KeyB) {
+        let Some(mut blueprint) = blueprint_tool.current.clone() else {
+            return;
+        };
+        if blueprint.name.is_empty() {
+            blueprint.name = format!("blueprint {}", library.blueprints.len() + 1);
+        }
+        let name = blueprint.name.clone();
+        match library.update(|library| library.blueprints.push(blueprint)) {
+            Ok(()) => info!("added {} to the blueprint library", name),
+            Err(e) => error!("could not update the blueprint library {:?}", e),
+        }
+    }
+    if keys.just_pressed(KeyCode::KeyN) && !library.blueprints.is_empty() {
+        let index = blueprint_tool.library_index % library.blueprints.len();
+        let blueprint = library.blueprints[index].clone();
+        info!("picked {} from the blueprint library", blueprint.name);
+        blueprint_tool.library_index = index + 1;
+        blueprint_tool.current = Some(blueprint);
+        start_tool(BlueprintMode::Pasting, &mut blueprint_tool);
+    }
+    if keys.just_pressed(KeyCode::KeyE) {
+        let Some(blueprint) = &blueprint_tool.current else {
+            return;
+        };
+        let Some(clipboard) = clipboard.0.as_mut() else {
+            error!("there is no clipboard to copy the blueprint to");
+            return;
+        };
+        let text = blueprint.to_text();
+        match clipboard.set_text(text.as_str()) {
+            Ok(()) => info!("copied blueprint {} to the clipboard", text),
+            Err(e) => error!("could not copy the blueprint to the clipboard {:?}", e),
+        }
+    }
+    if keys.just_pressed(KeyCode::KeyI) {
+        let Some(clipboard) = clipboard.0.as_mut() else {
+            error!("there is no clipboard to paste the blueprint from");
+            return;
+        };
+        let text = match clipboard.get_text() {
+            Ok(text) => text,
+            Err(e) => {
+                error!("could not read the blueprint from the clipboard {:?}", e);
+                return;
+            }
+        };
+        match Blueprint::from_text(&text) {
+            Ok(blueprint) => {
+                info!("imported blueprint {}", blueprint.name);
+                blueprint_tool.current = Some(blueprint);
+                start_tool(BlueprintMode::Pasting, &mut blueprint_tool);
+            }
+            Err(e) => error!("{}", e),
+        }
+    }
+}
+
+pub fn blueprint_selection_system(
+    input: Res<ButtonInput<MouseButton>>,
+    game_cursor: Res<GameCursor>,
+    world_grid: Res<WorldGrid>,
+    mut blueprint_tool: ResMut<BlueprintTool>,
+    building_q: Query<(&Building, &Transform, Option<&Active>), Without<Preview>>,
+) {
+    let BlueprintMode::Selecting { start } = blueprint_tool.mode else {
+        return;
+    };
+    let Some(cursor_position) = game_cursor.world_position else {
+        return;
+    };
+    let cursor_grid_position = world_grid.grid_position_from_world_position(cursor_position);
+    if input.just_pressed(MouseButton::Left) {
+        blueprint_tool.mode = BlueprintMode::Selecting {
+            start: Some(cursor_grid_position),
+        };
+        return;
+    }
+    let Some(start) = start else {
+        return;
+    };
+    if !input.just_released(MouseButton::Left) {
+        return;
+    }
+
+    let (min, max) = GridPosition::region(start, cursor_grid_position);
+    let mut entries = vec![];
+    for x in min.x..=max.x {
+        for y in min.y..=max.y {
+            let grid_position = GridPosition { x, y };
+            let Some(entity) = world_grid.get_building_entity(&grid_position) else {
+                continue;
+            };
+            let Ok((building, transform, active)) = building_q.get(entity) else {
+                continue;
+            };
+            entries.push(BlueprintEntry {
+                building_type: building.building_type.clone(),
+                grid_position: GridPosition {
+                    x: x - min.x,
+                    y: y - min.y,
+                },
+                grid_rotation: transform.grid_rotation(),
+                settings: BuildingSettings {
+                    active: active.is_some(),
+                },
+            });
+        }
+    }
+    if entries.is_empty() {
+        info!("no buildings in the selected region");
+        blueprint_tool.mode = BlueprintMode::Selecting { start: None };
+        return;
+    }
+    info!("captured a blueprint with {} buildings", entries.len());
+    blueprint_tool.current = Some(Blueprint {
+        name: String::new(),
+        entries,
+    });
+    blueprint_tool.mode = BlueprintMode::Pasting;
+}
+
+pub fn blueprint_paste_system(
+    mut commands: Commands,
+    mut shapes: ShapeCommands,
+    mut asset_server: ResMut<AssetServer>,
+    input: Res<ButtonInput<MouseButton>>,
+    game_cursor: Res<GameCursor>,
+    mut world_grid: ResMut<WorldGrid>,
+    mut inventory: ResMut<Inventory>,
+    blueprint_tool: Res<BlueprintTool>,
+    definitions: Res<BuildingDefinitions>,
+    research: Res<Research>,
+    tech_trees: Res<Assets<TechTree>>,
+    tech_tree_handle: Res<TechTreeHandle>,
+    mut building_placed_event: EventWriter<BuildingPlacedEvent>,
+    mut item_consumed_event: EventWriter<ItemConsumedEvent>,
+) {
+    if blueprint_tool.mode != BlueprintMode::Pasting || !input.just_pressed(MouseButton::Left) {
+        return;
+    }
+    let Some(blueprint) = &blueprint_tool.current else {
+        return;
+    };
+    let Some(cursor_position) = game_cursor.world_position else {
+        return;
+    };
+    let origin = world_grid.grid_position_from_world_position(cursor_position);
+    let tech_tree = tech_trees.get(&tech_tree_handle.0);
+
+    let mut skipped = 0;
+    let mut unaffordable = 0;
+    let mut locked = 0;
+    for entry in blueprint.entries.iter() {
+        let grid_position = origin + entry.grid_position;
+        if !research.is_building_unlocked(tech_tree, &entry.building_type) {
+            locked += 1;
+            continue;
+        }
+        if !can_place_building(
+            &world_grid,
+            &definitions,
+            &entry.building_type,
+            grid_position,
+        ) {
+            skipped += 1;
+            continue;
+        }
+        let cost = definitions.cost(&entry.building_type);
+        if !inventory.can_afford(&cost) {
+            unaffordable += 1;
+            continue;
+        }
+        if spawn_building_on_grid(
+            entry.building_type.clone(),
+            grid_position,
+            entry.grid_rotation,
+            entry.settings.active,
+            false,
+            &definitions,
+            &mut commands,
+            &mut asset_server,
+            &mut shapes,
+            &mut world_grid,
+            &mut building_placed_event,
+        )
+        .is_some()
+        {
+            inventory.spend(&cost);
+            item_consumed_event.send_batch(ItemConsumedEvent::for_cost(&cost));
+        }
+    }
+    if skipped > 0 {
+        warn!("{} buildings of the blueprint could not be placed", skipped);
+    }
+    if unaffordable > 0 {
+        warn!(
+            "not enough items for {} buildings of the blueprint",
+            unaffordable
+        );
+    }
+    if locked > 0 {
+        warn!(
+            "{} buildings of the blueprint are not researched yet",
+            locked
+        );
+    }
+}
+
+pub fn draw_blueprint_system(
+    mut painter: ShapePainter,
+    game_cursor: Res<GameCursor>,
+    world_grid: Res<WorldGrid>,
+    blueprint_tool: Res<BlueprintTool>,
+    definitions: Res<BuildingDefinitions>,
+) {
+    let Some(cursor_position) = game_cursor.world_position else {
+        return;
+    };
+    let cursor_grid_position = world_grid.grid_position_from_world_position(cursor_position);
+    let grid_size = world_grid.grid_size;
+
+    match &blueprint_tool.mode {
+        BlueprintMode::Idle => {}
+        BlueprintMode::Selecting { start } => {
+            let (min, max) =
+                GridPosition::region(start.unwrap_or(cursor_grid_position), cursor_grid_position);
+            let min_position = world_grid.grid_to_world(&min);
+            let max_position = world_grid.grid_to_world(&max);
+            let size = (max_position - min_position).xz().abs() + Vec2::splat(grid_size);
+
+            painter.hollow = false;
+            painter.color = Color::srgba(0.3, 0.6, 1.0, 0.25);
+            painter.transform =
+                Transform::from_translation((min_position + max_position) * 0.5 + Vec3::Y * 0.12)
+                    .with_rotation(Quat::from_rotation_x(TAU * 0.25));
+            painter.rect(size);
+        }
+        BlueprintMode::Pasting => {
+            let Some(blueprint) = &blueprint_tool.current else {
+                return;
+            };
+            let ghost_size = grid_size * 0.9;
+            for entry in blueprint.entries.iter() {
+                let grid_position = cursor_grid_position + entry.grid_position;
+                let color = if can_place_building(
+                    &world_grid,
+                    &definitions,
+                    &entry.building_type,
+                    grid_position,
+                ) {
+                    Color::srgba(0.3, 0.6, 1.0, 0.35)
+                } else {
+                    Color::srgba(0.9, 0.2, 0.2, 0.35)
+                };
+                let position = world_grid.grid_to_world(&grid_position) + Vec3::Y * 0.12;
+
+                painter.hollow = false;
+                painter.color = color;
+                painter.transform = Transform::from_translation(position)
+                    .with_rotation(Quat::from_rotation_x(TAU * 0.25));
+                painter.rect(Vec2::splat(ghost_size));
+
+                painter.transform = Transform::IDENTITY;
+                painter.thickness = 0.04;
+                painter.color = color.with_alpha(0.9);
+                let direction = entry.grid_rotation.get_direction();
+                painter.line(
+                    position - direction * ghost_size * 0.3,
+                    position + direction * ghost_size * 0.3,
+                );
+            }
+        }
+    }
+}
+
+pub fn reset_blueprint_system(mut blueprint_tool: ResMut<BlueprintTool>) {
+    blueprint_tool.mode = BlueprintMode::Idle;
+}
@@ -0,0 +1,45 @@
+use crate::blueprint::blueprint_components::*;
+use crate::blueprint::blueprint_systems::*;
+use crate::utilities::utility_methods::convert_legacy_building_types;
+use crate::AppState;
+use bevy::prelude::*;
+use bevy_persistent::prelude::*;
+
+pub mod blueprint_components;
+mod blueprint_systems;
+
+pub struct BlueprintPlugin;
+
+impl Plugin for BlueprintPlugin {
+    fn build(&self, app: &mut App) {
+        let persistence_dir = std::env::current_dir().unwrap().join("assets");
+        let library_path = persistence_dir.join("blueprints.ron");
+        let mut library = Persistent::<BlueprintLibrary>::builder()
+            .name("Blueprint Library")
+            .format(StorageFormat::RonPrettyWithStructNames)
+            .path(library_path.clone())
+            .default(BlueprintLibrary::default())
+            .build()
+            .expect("failed to initialize the blueprint library");
+        convert_legacy_building_types(&mut library, &library_path);
+        let clipboard = arboard::Clipboard::new()
+            .inspect_err(|e| warn!("no clipboard to share blueprints {:?}", e))
+            .ok();
+
+        app.insert_resource(library)
+            .insert_non_send_resource(BlueprintClipboard(clipboard))
+            .init_resource::<BlueprintTool>()
+            .add_systems(
+                Update,
+                (
+                    blueprint_input_system,
+                    blueprint_selection_system,
+                    blueprint_paste_system,
+                    draw_blueprint_system,
+                )
+                    .chain()
+                    .run_if(in_state(AppState::Game)),
+            )
+            .add_systems(OnExit(AppState::Game), reset_blueprint_system);
+    }
+}
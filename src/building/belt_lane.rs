@@ -0,0 +1,1073 @@
+use crate::building::building_components::BeltItem;
+use bevy::prelude::*;
+
+const GAP_EPSILON: f32 = 0.0001;
+
+/// Items on a conveyor stored as the gaps between them instead of absolute positions.
+///
+/// Items are ordered front (end of the belt) first. `gaps[i]` is the free distance in front of
+/// item `i`, either up to the item ahead of it or, for the front item, up to the end of the lane.
+/// Only the first gap that is still open can close, everything behind it moves along with it, so
+/// a compressed or stalled lane does not cost anything per tick.
+///
+/// A fenwick tree over the distance every item takes up (its gap plus spacing to the item ahead)
+/// allows finding the slot for a new item in logarithmic time. Adding an item at the start of the
+/// lane is logarithmic as well, inserting it in the middle shifts the items behind it and
+/// recomputes their part of the tree, so it costs linear time in the number of items behind the
+/// slot. `retain` walks the whole lane once.
+///
+/// A circular lane belongs to a closed loop, nothing ever blocks its items so they all move
+/// together. It only keeps an offset that rotates the stored positions around the loop.
+#[derive(Reflect, Debug, Clone, Default)]
+pub struct BeltLane {
+    length: f32,
+    circular: bool,
+    offset: f32,
+    //items before head already left the lane and only wait for the next compaction
+    head: usize,
+    items: Vec<BeltItem>,
+    gaps: Vec<f32>,
+    //index of the first gap that is not closed yet
+    active_gap: usize,
+    //distance the front item has to keep from the end, for an item waiting just past the end
+    end_clearance: f32,
+    #[reflect(ignore)]
+    tree: Vec<f64>,
+}
+
+impl BeltLane {
+    pub fn new(length: f32) -> Self {
+        Self {
+            length,
+            ..default()
+        }
+    }
+
+    pub fn new_circular(length: f32) -> Self {
+        Self {
+            length,
+            circular: true,
+            ..default()
+        }
+    }
+
+    ///Builds a lane from items and their distance from the start of the lane, items that would
+    ///overlap or are outside of the lane are returned
+    pub fn from_items(
+        length: f32,
+        circular: bool,
+        mut items: Vec<(f32, BeltItem)>,
+    ) -> (Self, Vec<(f32, BeltItem)>) {
+        let mut lane = if circular {
+            Self::new_circular(length)
+        } else {
+            Self::new(length)
+        };
+        let mut rejected = vec![];
+        items.sort_by(|a, b| b.0.total_cmp(&a.0));
+        for (distance, item) in items {
+            if !lane.insert(distance, item.clone()) {
+                rejected.push((distance, item));
+            }
+        }
+        (lane, rejected)
+    }
+
+    pub fn length(&self) -> f32 {
+        self.length
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len() - self.head
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    ///Iterates over all items front first together with their distance from the start of the lane
+    pub fn iter(&self) -> impl Iterator<Item = (f32, &BeltItem)> + '_ {
+        let mut from_end = 0.0;
+        (self.head..self.items.len()).map(move |i| {
+            from_end += self.span(i);
+            (
+                self.to_lane_distance(self.length - from_end),
+                &self.items[i],
+            )
+        })
+    }
+
+    pub fn front(&self) -> Option<&BeltItem> {
+        self.items.get(self.head)
+    }
+
+    ///The last item together with its distance from the start of the lane
+    pub fn back(&self) -> Option<(f32, &BeltItem)> {
+        let item = self.items.last().filter(|_| !self.is_empty())?;
+        let from_end = self.prefix(self.items.len());
+        Some((self.to_lane_distance(self.length - from_end), item))
+    }
+
+    ///The front item has reached the end of the lane, never happens on a circular lane
+    pub fn front_at_end(&self) -> bool {
+        !self.circular
+            && self
+                .gaps
+                .get(self.head)
+                .is_some_and(|gap| *gap <= GAP_EPSILON)
+    }
+
+    ///All items are pushed together against the end of the lane, nothing moves anymore
+    pub fn is_compressed(&self) -> bool {
+        !self.circular && self.active_gap >= self.items.len()
+    }
+
+    ///Moves all items that can move by `distance`, returns how many items at the front did not move
+    pub fn advance(&mut self, distance: f32) -> usize {
+        if self.circular {
+            if self.length > 0.0 {
+                self.offset = (self.offset + distance).rem_euclid(self.length);
+            }
+            return 0;
+        }
+        let mut remaining = distance;
+        loop {
+            while self.active_gap < self.items.len()
+                && self.free_gap(self.active_gap) <= GAP_EPSILON
+            {
+                if self.active_gap != self.head {
+                    self.gaps[self.active_gap] = 0.0;
+                }
+                self.active_gap += 1;
+            }
+            if remaining <= 0.0 || self.active_gap >= self.items.len() {
+                break;
+            }
+            //once the gap is closed the items behind it keep moving with what is left
+            let moved = self.free_gap(self.active_gap).min(remaining);
+            self.gaps[self.active_gap] -= moved;
+            remaining -= moved;
+            self.tree_add(self.active_gap, -moved);
+        }
+        self.active_gap.saturating_sub(self.head)
+    }
+
+    ///Keeps the front item this far away from the end of the lane
+    pub fn set_end_clearance(&mut self, clearance: f32) {
+        if self.circular {
+            return;
+        }
+        //the front item can move again once the clearance shrinks
+        if clearance < self.end_clearance
+            && self
+                .gaps
+                .get(self.head)
+                .is_some_and(|gap| *gap > clearance + GAP_EPSILON)
+        {
+            self.active_gap = self.head;
+        }
+        self.end_clearance = clearance;
+    }
+
+    pub fn pop_front(&mut self) -> Option<BeltItem> {
+        let index = self.head;
+        if index >= self.items.len() {
+            return None;
+        }
+        let span = self.span(index);
+        let item = self.items[index].clone();
+        self.tree_add(index, -span);
+        self.head += 1;
+
+        if self.head < self.items.len() {
+            //the next item inherits the freed space, as the new front it needs no spacing anymore
+            let spacing = (item.item_width + self.items[self.head].item_width) * 0.5;
+            self.gaps[self.head] += span + spacing;
+            self.tree_add(self.head, span);
+        }
+        self.active_gap = self.head;
+
+        if self.head * 2 >= self.items.len() {
+            self.compact();
+        }
+        Some(item)
+    }
+
+    pub fn has_space_at(&self, distance: f32, item_width: f32) -> bool {
+        self.free_slot(self.length - self.to_stored_distance(distance), item_width)
+            .is_some()
+    }
+
+    ///Inserts the item centered at `distance` from the start of the lane, returns false if there is no space
+    pub fn insert(&mut self, distance: f32, item: BeltItem) -> bool {
+        let from_end = self.length - self.to_stored_distance(distance);
+        let Some(index) = self.free_slot(from_end, item.item_width) else {
+            return false;
+        };
+
+        let (front_center, spacing) = if index > self.head {
+            let ahead = &self.items[index - 1];
+            (
+                self.center_from_end(index - 1),
+                (ahead.item_width + item.item_width) * 0.5,
+            )
+        } else {
+            (0.0, 0.0)
+        };
+        let gap = (from_end - front_center - spacing).max(0.0);
+
+        if index == self.items.len() {
+            self.push_back(item, gap);
+        } else {
+            let behind_center = self.center_from_end(index);
+            let behind_spacing = (item.item_width + self.items[index].item_width) * 0.5;
+            self.gaps[index] = (behind_center - from_end - behind_spacing).max(0.0);
+            self.items.insert(index, item);
+            self.gaps.insert(index, gap);
+            if self.active_gap > index {
+                self.active_gap = index;
+            }
+            self.rebuild_tree_from(index);
+        }
+        true
+    }
+
+    ///Keeps only the items for which the predicate returns true, gets the distance from the start
+    pub fn retain(&mut self, mut f: impl FnMut(f32, &BeltItem) -> bool) {
+        //centers of the kept items, the positions of the removed ones turn into free space
+        let mut centers = vec![None; self.items.len()];
+        let mut from_end = 0.0;
+        for (i, center) in centers.iter_mut().enumerate().skip(self.head) {
+            from_end += self.span(i);
+            if f(
+                self.to_lane_distance(self.length - from_end),
+                &self.items[i],
+            ) {
+                *center = Some(from_end);
+            }
+        }
+        if centers.iter().flatten().count() == self.len() {
+            return;
+        }
+
+        let mut items = vec![];
+        let mut gaps = vec![];
+        let mut front_center = 0.0;
+        let items_before = std::mem::take(&mut self.items);
+        for (item, center) in items_before.into_iter().zip(centers) {
+            let Some(center) = center else {
+                continue;
+            };
+            let spacing = items.last().map_or(0.0, |ahead: &BeltItem| {
+                (ahead.item_width + item.item_width) * 0.5
+            });
+            gaps.push((center - front_center - spacing).max(0.0));
+            front_center = center;
+            items.push(item);
+        }
+        self.items = items;
+        self.gaps = gaps;
+        self.head = 0;
+        self.active_gap = (0..self.items.len())
+            .find(|i| self.free_gap(*i) > GAP_EPSILON)
+            .unwrap_or(self.items.len());
+        self.rebuild_tree_from(0);
+    }
+
+    ///Removes all items and returns them with their distance from the start
+    pub fn drain(&mut self) -> Vec<(f32, BeltItem)> {
+        let items = self
+            .iter()
+            .map(|(distance, item)| (distance, item.clone()))
+            .collect();
+        *self = Self::from_items(self.length, self.circular, vec![]).0;
+        items
+    }
+
+    fn to_lane_distance(&self, stored_distance: f32) -> f32 {
+        if !self.circular || self.length <= 0.0 {
+            return stored_distance;
+        }
+        (stored_distance + self.offset).rem_euclid(self.length)
+    }
+
+    fn to_stored_distance(&self, distance: f32) -> f32 {
+        if !self.circular || self.length <= 0.0 {
+            return distance;
+        }
+        (distance - self.offset).rem_euclid(self.length)
+    }
+
+    fn spacing(&self, index: usize) -> f32 {
+        if index == self.head {
+            0.0
+        } else {
+            (self.items[index - 1].item_width + self.items[index].item_width) * 0.5
+        }
+    }
+
+    ///The part of the gap the item can still close
+    fn free_gap(&self, index: usize) -> f32 {
+        if index == self.head {
+            self.gaps[index] - self.end_clearance
+        } else {
+            self.gaps[index]
+        }
+    }
+
+    fn span(&self, index: usize) -> f32 {
+        self.gaps[index] + self.spacing(index)
+    }
+
+    fn center_from_end(&self, index: usize) -> f32 {
+        self.prefix(index + 1)
+    }
+
+    ///Index at which an item centered at `from_end` would have to be inserted, if there is space
+    fn free_slot(&self, from_end: f32, item_width: f32) -> Option<usize> {
+        if from_end < -GAP_EPSILON || from_end > self.length + GAP_EPSILON {
+            return None;
+        }
+        let index = self.lower_bound(from_end).max(self.head);
+        if index > self.head {
+            let ahead = &self.items[index - 1];
+            let distance = from_end - self.center_from_end(index - 1);
+            if distance < (ahead.item_width + item_width) * 0.5 - GAP_EPSILON {
+                return None;
+            }
+        }
+        if index < self.items.len() {
+            let behind = &self.items[index];
+            let distance = self.center_from_end(index) - from_end;
+            if distance < (behind.item_width + item_width) * 0.5 - GAP_EPSILON {
+                return None;
+            }
+        }
+        if self.circular && !self.is_empty() {
+            //on a loop the items at both ends of the lane are neighbours across the seam
+            let last = self.items.len() - 1;
+            let front = &self.items[self.head];
+            let back = &self.items[last];
+            let to_front = self.center_from_end(self.head) + self.length - from_end;
+            let to_back = from_end + self.length - self.center_from_end(last);
+            if to_front < (front.item_width + item_width) * 0.5 - GAP_EPSILON
+                || to_back < (back.item_width + item_width) * 0.5 - GAP_EPSILON
+            {
+                return None;
+            }
+        }
+        Some(index)
+    }
+
+    fn push_back(&mut self, item: BeltItem, gap: f32) {
+        let index = self.items.len();
+        self.items.push(item);
+        self.gaps.push(gap);
+        //a new fenwick node covers the range (k - lowbit(k), k] of the 1-based index k
+        let k = index + 1;
+        let covered_from = k - (k & k.wrapping_neg());
+        let node = self.prefix(index) - self.prefix(covered_from) + self.span(index);
+        self.tree.push(node as f64);
+        if self.active_gap >= index && gap > GAP_EPSILON {
+            self.active_gap = index;
+        }
+    }
+
+    fn compact(&mut self) {
+        self.items.drain(..self.head);
+        self.gaps.drain(..self.head);
+        self.active_gap -= self.head.min(self.active_gap);
+        self.head = 0;
+        self.rebuild_tree_from(0);
+    }
+
+    ///Recomputes the fenwick nodes that cover the item at `index` or one behind it, the nodes in
+    ///front of it stay as they are
+    fn rebuild_tree_from(&mut self, index: usize) {
+        let n = self.items.len();
+        self.tree.resize(n, 0.0);
+        let mut prefixes = Vec::with_capacity(n - index + 1);
+        prefixes.push(self.tree_prefix(index));
+        for i in index..n {
+            let span = if i < self.head { 0.0 } else { self.span(i) };
+            prefixes.push(prefixes[i - index] + span as f64);
+        }
+        for k in index + 1..=n {
+            let covered_from = k - (k & k.wrapping_neg());
+            let before = if covered_from >= index {
+                prefixes[covered_from - index]
+            } else {
+                self.tree_prefix(covered_from)
+            };
+            self.tree[k - 1] = prefixes[k - index] - before;
+        }
+    }
+
+    fn tree_add(&mut self, index: usize, delta: f32) {
+        let mut k = index + 1;
+        while k <= self.tree.len() {
+            self.tree[k - 1] += delta as f64;
+            k += k & k.wrapping_neg();
+        }
+    }
+
+    ///Sum of the spans of the first `count` items
+    fn prefix(&self, count: usize) -> f32 {
+        self.tree_prefix(count) as f32
+    }
+
+    fn tree_prefix(&self, count: usize) -> f64 {
+        let mut k = count;
+        let mut sum = 0.0;
+        while k > 0 {
+            sum += self.tree[k - 1];
+            k &= k - 1;
+        }
+        sum
+    }
+
+    ///Index of the first item whose center is at least `from_end` away from the end of the lane
+    fn lower_bound(&self, from_end: f32) -> usize {
+        let n = self.tree.len();
+        let mut position = 0;
+        let mut remaining = from_end as f64;
+        let mut step = if n == 0 { 0 } else { 1 << n.ilog2() };
+        while step > 0 {
+            if position + step <= n && self.tree[position + step - 1] < remaining {
+                position += step;
+                remaining -= self.tree[position - 1];
+            }
+            step >>= 1;
+        }
+        position
+    }
+}
+
+/// All items of a conveyor, split into zones that move their items at their own speed.
+///
+/// Every zone is a lane of its own. An item that reaches the end of a zone is handed to the start
+/// of the zone ahead of it once there is space, so a slow zone backs up the faster zone behind it.
+/// A loop with a single speed keeps one circular lane, a loop with several hands its items from
+/// the last zone back to the first one.
+#[derive(Reflect, Debug, Clone, Default)]
+pub struct BeltTrack {
+    //zones from the start to the end of the belt
+    zones: Vec<TrackZone>,
+    circular: bool,
+}
+
+#[derive(Reflect, Debug, Clone, Default)]
+struct TrackZone {
+    //distance of the zone from the start of the track
+    start: f32,
+    speed: f32,
+    lane: BeltLane,
+}
+
+impl BeltTrack {
+    ///Zones are given as length and speed, from the start to the end of the belt
+    pub fn new(zones: &[(f32, f32)], circular: bool) -> Self {
+        let single = zones.len() == 1;
+        let mut start = 0.0;
+        let zones = zones
+            .iter()
+            .map(|(length, speed)| {
+                let lane = if circular && single {
+                    BeltLane::new_circular(*length)
+                } else {
+                    BeltLane::new(*length)
+                };
+                let zone = TrackZone {
+                    start,
+                    speed: *speed,
+                    lane,
+                };
+                start += length;
+                zone
+            })
+            .collect();
+        Self { zones, circular }
+    }
+
+    ///Builds a track from items and their distance from the start of the track, items that would
+    ///overlap or are outside of the track are returned
+    pub fn from_items(
+        zones: &[(f32, f32)],
+        circular: bool,
+        mut items: Vec<(f32, BeltItem)>,
+    ) -> (Self, Vec<(f32, BeltItem)>) {
+        let mut track = Self::new(zones, circular);
+        let mut rejected = vec![];
+        items.sort_by(|a, b| b.0.total_cmp(&a.0));
+        for (distance, item) in items {
+            if !track.insert(distance, item.clone()) {
+                rejected.push((distance, item));
+            }
+        }
+        (track, rejected)
+    }
+
+    pub fn length(&self) -> f32 {
+        self.zones
+            .last()
+            .map_or(0.0, |zone| zone.start + zone.lane.length())
+    }
+
+    pub fn len(&self) -> usize {
+        self.zones.iter().map(|zone| zone.lane.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.zones.iter().all(|zone| zone.lane.is_empty())
+    }
+
+    pub fn zone_count(&self) -> usize {
+        self.zones.len()
+    }
+
+    ///Iterates over all items front first together with their distance from the start of the track
+    pub fn iter(&self) -> impl Iterator<Item = (f32, &BeltItem)> + '_ {
+        self.zones.iter().rev().flat_map(|zone| {
+            zone.lane
+                .iter()
+                .map(move |(distance, item)| (zone.start + distance, item))
+        })
+    }
+
+    pub fn front(&self) -> Option<&BeltItem> {
+        self.zones.last().and_then(|zone| zone.lane.front())
+    }
+
+    ///The front item has reached the end of the track, never happens on a loop
+    pub fn front_at_end(&self) -> bool {
+        !self.circular
+            && self
+                .zones
+                .last()
+                .is_some_and(|zone| zone.lane.front_at_end())
+    }
+
+    ///Nothing on the track moves anymore
+    pub fn is_compressed(&self) -> bool {
+        (0..self.zones.len()).all(|i| {
+            let lane = &self.zones[i].lane;
+            lane.is_empty() || (lane.is_compressed() && !self.can_hand_over(i))
+        })
+    }
+
+    ///Moves the items of every zone by its speed, front zone first so the items it hands over
+    ///make space for the zones behind it
+    pub fn advance(&mut self, delta_secs: f32) {
+        let count = self.zones.len();
+        for i in (0..count).rev() {
+            let clearance = self.end_clearance(i);
+            let zone = &mut self.zones[i];
+            zone.lane.set_end_clearance(clearance);
+            zone.lane.advance(zone.speed * delta_secs);
+            if i + 1 < count {
+                self.hand_over(i);
+            }
+        }
+        //the last zone of a loop hands over once the first one moved, or the item moves twice
+        if self.circular && count > 1 {
+            self.hand_over(count - 1);
+        }
+    }
+
+    pub fn pop_front(&mut self) -> Option<BeltItem> {
+        self.zones.last_mut().and_then(|zone| zone.lane.pop_front())
+    }
+
+    pub fn has_space_at(&self, distance: f32, item_width: f32) -> bool {
+        self.zone_index(distance)
+            .is_some_and(|i| self.has_space_in_zone(i, distance - self.zones[i].start, item_width))
+    }
+
+    ///Inserts the item centered at `distance` from the start of the track, returns false if there
+    ///is no space
+    pub fn insert(&mut self, distance: f32, item: BeltItem) -> bool {
+        let Some(i) = self.zone_index(distance) else {
+            return false;
+        };
+        let local_distance = distance - self.zones[i].start;
+        if !self.has_space_in_zone(i, local_distance, item.item_width) {
+            return false;
+        }
+        self.zones[i].lane.insert(local_distance, item)
+    }
+
+    ///Keeps only the items for which the predicate returns true, gets the distance from the start
+    pub fn retain(&mut self, mut f: impl FnMut(f32, &BeltItem) -> bool) {
+        for zone in self.zones.iter_mut() {
+            let start = zone.start;
+            zone.lane.retain(|distance, item| f(start + distance, item));
+        }
+    }
+
+    ///Removes all items and returns them with their distance from the start
+    pub fn drain(&mut self) -> Vec<(f32, BeltItem)> {
+        let mut items = vec![];
+        for zone in self.zones.iter_mut().rev() {
+            let start = zone.start;
+            items.extend(
+                zone.lane
+                    .drain()
+                    .into_iter()
+                    .map(|(distance, item)| (start + distance, item)),
+            );
+        }
+        items
+    }
+
+    fn zone_index(&self, distance: f32) -> Option<usize> {
+        if distance < -GAP_EPSILON || distance > self.length() + GAP_EPSILON {
+            return None;
+        }
+        let index = self
+            .zones
+            .iter()
+            .rposition(|zone| zone.start <= distance)
+            .unwrap_or(0);
+        (index < self.zones.len()).then_some(index)
+    }
+
+    ///The zone that gets the items reaching the end of this one
+    fn zone_ahead(&self, i: usize) -> Option<usize> {
+        if i + 1 < self.zones.len() {
+            Some(i + 1)
+        } else if self.circular && self.zones.len() > 1 {
+            Some(0)
+        } else {
+            None
+        }
+    }
+
+    fn zone_behind(&self, i: usize) -> Option<usize> {
+        if i > 0 {
+            Some(i - 1)
+        } else if self.circular && self.zones.len() > 1 {
+            Some(self.zones.len() - 1)
+        } else {
+            None
+        }
+    }
+
+    fn hand_over(&mut self, i: usize) {
+        if !self.can_hand_over(i) {
+            return;
+        }
+        let Some(next) = self.zone_ahead(i) else {
+            return;
+        };
+        let item = self.zones[i].lane.pop_front().unwrap();
+        self.zones[next].lane.insert(0.0, item);
+    }
+
+    ///How far the front item of the zone has to stay away from its end to not run into the last
+    ///item of the zone ahead of it
+    fn end_clearance(&self, i: usize) -> f32 {
+        let (Some(ahead), Some(item)) = (self.zone_ahead(i), self.zones[i].lane.front()) else {
+            return 0.0;
+        };
+        let Some((back, back_item)) = self.zones[ahead].lane.back() else {
+            return 0.0;
+        };
+        ((item.item_width + back_item.item_width) * 0.5 - back).max(0.0)
+    }
+
+    ///The front item of the zone waits at its end and the zone ahead of it has space
+    fn can_hand_over(&self, i: usize) -> bool {
+        let lane = &self.zones[i].lane;
+        let (Some(next), Some(item)) = (self.zone_ahead(i), lane.front()) else {
+            return false;
+        };
+        lane.front_at_end()
+            && self.zones[next].lane.has_space_at(0.0, item.item_width)
+            && self.fits_behind_zone_ahead(next, 0.0, item.item_width)
+    }
+
+    fn has_space_in_zone(&self, i: usize, local_distance: f32, item_width: f32) -> bool {
+        self.zones[i].lane.has_space_at(local_distance, item_width)
+            && self.fits_behind_zone_ahead(i, local_distance, item_width)
+            && self.fits_ahead_of_zone_behind(i, local_distance, item_width)
+    }
+
+    ///Items close to the end of a zone can overlap the last item of the zone ahead of it
+    fn fits_behind_zone_ahead(&self, i: usize, local_distance: f32, item_width: f32) -> bool {
+        let Some(ahead) = self.zone_ahead(i) else {
+            return true;
+        };
+        let Some((back, item)) = self.zones[ahead].lane.back() else {
+            return true;
+        };
+        //the zone ahead of the last one is the first one, one loop further
+        let wrap = if ahead < i { self.length() } else { 0.0 };
+        let gap = self.zones[ahead].start + wrap + back - self.zones[i].start - local_distance;
+        gap >= (item.item_width + item_width) * 0.5 - GAP_EPSILON
+    }
+
+    ///Items close to the start of a zone can overlap the front item of the zone behind it
+    fn fits_ahead_of_zone_behind(&self, i: usize, local_distance: f32, item_width: f32) -> bool {
+        let Some(behind) = self.zone_behind(i) else {
+            return true;
+        };
+        let Some((front, item)) = self.zones[behind].lane.iter().next() else {
+            return true;
+        };
+        let wrap = if behind > i { self.length() } else { 0.0 };
+        let gap = self.zones[i].start + wrap + local_distance - self.zones[behind].start - front;
+        gap >= (item.item_width + item_width) * 0.5 - GAP_EPSILON
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::building::building_components::ItemType;
+
+    fn item() -> BeltItem {
+        BeltItem {
+            item_type: ItemType::YellowBile,
+            item_width: 0.2,
+        }
+    }
+
+    fn distances(lane: &BeltLane) -> Vec<f32> {
+        lane.iter().map(|(distance, _)| distance).collect()
+    }
+
+    fn assert_distances(lane: &BeltLane, expected: &[f32]) {
+        let actual = distances(lane);
+        assert_eq!(actual.len(), expected.len(), "{actual:?} != {expected:?}");
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 0.001, "{actual:?} != {expected:?}");
+        }
+    }
+
+    #[test]
+    fn items_compress_against_the_end() {
+        let mut lane = BeltLane::new(3.0);
+        assert!(lane.insert(1.0, item()));
+        assert!(lane.insert(0.5, item()));
+        assert!(lane.insert(0.1, item()));
+
+        assert_eq!(lane.advance(10.0), 3);
+        assert!(lane.is_compressed());
+        assert!(lane.front_at_end());
+        assert_distances(&lane, &[3.0, 2.8, 2.6]);
+    }
+
+    #[test]
+    fn leftover_distance_moves_the_items_behind() {
+        let mut lane = BeltLane::new(10.0);
+        assert!(lane.insert(9.0, item()));
+        assert!(lane.insert(5.0, item()));
+
+        assert_eq!(lane.advance(2.0), 1);
+        assert_distances(&lane, &[10.0, 7.0]);
+        assert!(!lane.is_compressed());
+
+        assert_eq!(lane.advance(0.5), 1);
+        assert_distances(&lane, &[10.0, 7.5]);
+    }
+
+    #[test]
+    fn insert_into_a_gap() {
+        let mut lane = BeltLane::new(10.0);
+        assert!(lane.insert(9.0, item()));
+        assert!(lane.insert(5.0, item()));
+
+        assert!(lane.insert(7.0, item()));
+        assert_distances(&lane, &[9.0, 7.0, 5.0]);
+
+        //overlaps the items around it
+        assert!(!lane.insert(8.9, item()));
+        assert!(!lane.has_space_at(5.1, 0.2));
+        assert!(lane.has_space_at(6.0, 0.2));
+
+        lane.advance(1.0);
+        assert_distances(&lane, &[10.0, 8.0, 6.0]);
+    }
+
+    #[test]
+    fn insert_into_a_closed_lane_keeps_it_moving() {
+        let mut lane = BeltLane::new(2.0);
+        assert!(lane.insert(1.0, item()));
+        lane.advance(5.0);
+        assert!(lane.is_compressed());
+
+        assert!(lane.insert(0.5, item()));
+        assert!(!lane.is_compressed());
+        lane.advance(5.0);
+        assert_distances(&lane, &[2.0, 1.8]);
+    }
+
+    #[test]
+    fn pop_front_frees_the_end() {
+        let mut lane = BeltLane::new(2.0);
+        for distance in [1.0, 0.6, 0.2] {
+            assert!(lane.insert(distance, item()));
+        }
+        lane.advance(5.0);
+        assert_distances(&lane, &[2.0, 1.8, 1.6]);
+
+        assert!(lane.pop_front().is_some());
+        assert_eq!(lane.len(), 2);
+        assert!(!lane.front_at_end());
+        assert_distances(&lane, &[1.8, 1.6]);
+
+        assert_eq!(lane.advance(0.1), 0);
+        assert_distances(&lane, &[1.9, 1.7]);
+        lane.advance(0.1);
+        assert!(lane.front_at_end());
+
+        assert!(lane.pop_front().is_some());
+        assert!(lane.pop_front().is_some());
+        assert!(lane.pop_front().is_none());
+        assert!(lane.is_empty());
+    }
+
+    #[test]
+    fn retain_keeps_positions() {
+        let mut lane = BeltLane::new(5.0);
+        for distance in [4.0, 3.0, 2.0] {
+            assert!(lane.insert(distance, item()));
+        }
+        lane.retain(|distance, _| distance != 3.0);
+        assert_distances(&lane, &[4.0, 2.0]);
+    }
+
+    #[test]
+    fn retain_frees_the_space_of_removed_items() {
+        let mut lane = BeltLane::new(2.0);
+        for distance in [1.0, 0.6, 0.2] {
+            assert!(lane.insert(distance, item()));
+        }
+        lane.advance(5.0);
+        assert!(lane.is_compressed());
+
+        //the item behind the removed one can close up again
+        lane.retain(|distance, _| (distance - 1.8).abs() > 0.001);
+        assert!(!lane.is_compressed());
+        lane.advance(5.0);
+        assert_distances(&lane, &[2.0, 1.8]);
+        assert!(lane.is_compressed());
+    }
+
+    #[test]
+    fn inserts_in_the_middle_keep_the_tree_in_sync() {
+        let mut lane = BeltLane::new(20.0);
+        for i in 0..20 {
+            assert!(lane.insert(19.5 - i as f32, item()));
+        }
+        lane.pop_front();
+        for i in 0..19 {
+            assert!(lane.insert(18.0 - i as f32, item()));
+        }
+        let mut from_end = 0.0;
+        for i in lane.head..lane.items.len() {
+            from_end += lane.span(i);
+            assert!((lane.prefix(i + 1) - from_end).abs() < 0.001);
+        }
+        assert_eq!(lane.len(), 38);
+        assert!(!lane.has_space_at(10.4, 0.2));
+        assert!(lane.has_space_at(10.25, 0.2));
+    }
+
+    #[test]
+    fn circular_lane_rotates() {
+        let mut lane = BeltLane::new_circular(4.0);
+        assert!(lane.insert(1.0, item()));
+        assert!(lane.insert(3.0, item()));
+
+        assert_eq!(lane.advance(1.5), 0);
+        let mut positions = distances(&lane);
+        positions.sort_by(f32::total_cmp);
+        assert!((positions[0] - 0.5).abs() < 0.001, "{positions:?}");
+        assert!((positions[1] - 2.5).abs() < 0.001, "{positions:?}");
+        //the items at both ends are neighbours across the seam
+        assert!(!lane.has_space_at(0.4, 0.2));
+    }
+
+    #[test]
+    fn circular_lane_never_blocks() {
+        let mut lane = BeltLane::new_circular(2.0);
+        for distance in [1.8, 1.2, 0.4] {
+            assert!(lane.insert(distance, item()));
+        }
+        for _ in 0..100 {
+            assert_eq!(lane.advance(0.37), 0);
+            assert!(!lane.front_at_end());
+            assert!(!lane.is_compressed());
+        }
+        //the items keep their spacing while they go around, across the seam included
+        let mut positions = distances(&lane);
+        positions.sort_by(f32::total_cmp);
+        assert_eq!(positions.len(), 3);
+        let mut spacings = vec![
+            positions[1] - positions[0],
+            positions[2] - positions[1],
+            positions[0] + 2.0 - positions[2],
+        ];
+        spacings.sort_by(f32::total_cmp);
+        for (spacing, expected) in spacings.iter().zip([0.6, 0.6, 0.8]) {
+            assert!((spacing - expected).abs() < 0.001, "{positions:?}");
+        }
+    }
+
+    fn track_distances(track: &BeltTrack) -> Vec<f32> {
+        track.iter().map(|(distance, _)| distance).collect()
+    }
+
+    fn assert_track_distances(track: &BeltTrack, expected: &[f32]) {
+        let actual = track_distances(track);
+        assert_eq!(actual.len(), expected.len(), "{actual:?} != {expected:?}");
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 0.001, "{actual:?} != {expected:?}");
+        }
+    }
+
+    #[test]
+    fn zones_move_at_their_own_speed() {
+        let mut track = BeltTrack::new(&[(2.0, 1.0), (2.0, 2.0)], false);
+        assert!(track.insert(0.0, item()));
+        assert!(track.insert(2.5, item()));
+
+        track.advance(0.5);
+        assert_track_distances(&track, &[3.5, 0.5]);
+    }
+
+    #[test]
+    fn items_cross_into_the_zone_ahead() {
+        let mut track = BeltTrack::new(&[(1.0, 1.0), (3.0, 2.0)], false);
+        assert!(track.insert(0.5, item()));
+
+        track.advance(0.5);
+        assert_track_distances(&track, &[1.0]);
+        track.advance(0.5);
+        assert_track_distances(&track, &[2.0]);
+
+        track.advance(5.0);
+        assert!(track.front_at_end());
+        assert!(track.is_compressed());
+        assert!(track.pop_front().is_some());
+        assert!(track.is_empty());
+    }
+
+    #[test]
+    fn full_zone_backs_up_the_zone_behind() {
+        let mut track = BeltTrack::new(&[(2.0, 4.0), (0.5, 1.0)], false);
+        for distance in [1.5, 1.0, 0.5, 0.0] {
+            assert!(track.insert(distance, item()));
+        }
+        for _ in 0..10 {
+            track.advance(0.25);
+        }
+        //the last item can't enter the full zone and keeps its distance to the one ahead of it
+        assert_track_distances(&track, &[2.5, 2.3, 2.1, 1.9]);
+        assert!(track.is_compressed());
+        assert!(!track.has_space_at(1.8, 0.2));
+        assert!(!track.has_space_at(2.0, 0.2));
+        assert!(track.has_space_at(1.6, 0.2));
+    }
+
+    #[test]
+    fn loop_with_zones_hands_items_around() {
+        let mut track = BeltTrack::new(&[(1.0, 1.0), (1.0, 2.0)], true);
+        assert!(track.insert(0.5, item()));
+        assert!(!track.front_at_end());
+
+        track.advance(0.5);
+        assert_track_distances(&track, &[1.0]);
+        track.advance(0.25);
+        assert_track_distances(&track, &[1.5]);
+        track.advance(0.25);
+        assert_track_distances(&track, &[0.0]);
+    }
+
+    #[test]
+    fn item_goes_around_a_loop_with_zones() {
+        let mut track = BeltTrack::new(&[(1.0, 1.0), (1.0, 2.0)], true);
+        assert!(track.insert(0.5, item()));
+
+        //half a unit in the slow zone, the fast zone and again half in the slow zone
+        for _ in 0..6 {
+            track.advance(0.25);
+        }
+        assert_track_distances(&track, &[0.5]);
+    }
+
+    #[test]
+    fn loop_with_zones_keeps_its_items() {
+        let mut track = BeltTrack::new(&[(1.0, 1.0), (1.0, 2.0)], true);
+        for distance in [1.75, 1.25, 0.75, 0.25] {
+            assert!(track.insert(distance, item()));
+        }
+        for _ in 0..200 {
+            track.advance(0.05);
+            assert!(!track.front_at_end());
+        }
+        let mut positions = track_distances(&track);
+        assert_eq!(positions.len(), 4);
+        positions.sort_by(f32::total_cmp);
+        //no item ran into another one, across the seam included
+        for pair in positions.windows(2) {
+            assert!(pair[1] - pair[0] >= 0.2 - 0.001, "{positions:?}");
+        }
+        assert!(
+            positions[0] + 2.0 - positions[3] >= 0.2 - 0.001,
+            "{positions:?}"
+        );
+    }
+
+    #[test]
+    fn split_track_keeps_the_items_on_both_parts() {
+        let mut track = BeltTrack::new(&[(2.0, 1.0), (2.0, 2.0)], false);
+        for distance in [3.5, 2.5, 1.5, 0.5] {
+            assert!(track.insert(distance, item()));
+        }
+        let (before, after): (Vec<_>, Vec<_>) = track
+            .drain()
+            .into_iter()
+            .partition(|(distance, _)| *distance < 2.0);
+        assert!(track.is_empty());
+
+        let (before, rejected) = BeltTrack::from_items(&[(2.0, 1.0)], false, before);
+        assert!(rejected.is_empty());
+        assert_track_distances(&before, &[1.5, 0.5]);
+        let after = after
+            .into_iter()
+            .map(|(distance, item)| (distance - 2.0, item))
+            .collect();
+        let (after, rejected) = BeltTrack::from_items(&[(2.0, 2.0)], false, after);
+        assert!(rejected.is_empty());
+        assert_track_distances(&after, &[1.5, 0.5]);
+    }
+
+    #[test]
+    fn merged_track_orders_the_items_front_first() {
+        //the items of the conveyor behind are appended after the ones of the conveyor ahead
+        let items = [3.0, 2.2, 0.8, 1.4]
+            .into_iter()
+            .map(|distance| (distance, item()))
+            .collect();
+        let (mut track, rejected) = BeltTrack::from_items(&[(4.0, 1.0)], false, items);
+        assert!(rejected.is_empty());
+        assert_track_distances(&track, &[3.0, 2.2, 1.4, 0.8]);
+
+        track.advance(10.0);
+        assert_track_distances(&track, &[4.0, 3.8, 3.6, 3.4]);
+    }
+
+    #[test]
+    fn overlapping_items_are_returned() {
+        let items = vec![(1.0, item()), (1.1, item()), (5.0, item())];
+        let (track, rejected) = BeltTrack::from_items(&[(2.0, 1.0)], false, items);
+        assert_track_distances(&track, &[1.1]);
+        let rejected = rejected
+            .iter()
+            .map(|(distance, _)| *distance)
+            .collect::<Vec<_>>();
+        assert_eq!(rejected, vec![5.0, 1.0]);
+    }
+}
@@ -1,34 +1,298 @@
+use crate::building::building_definitions::{BuildingBehaviour, BuildingDefinition};
 use crate::utilities::utility_methods::RoundBeltExt;
 use crate::world_grid::world_gird_components::*;
-use bevy::color::palettes::css::YELLOW;
+use crate::AppState;
+use bevy::color::palettes::css::{DARK_GRAY, DEEP_SKY_BLUE, GRAY, LIME, ORANGE, RED, YELLOW};
 use bevy::prelude::TimerMode::Repeating;
 use bevy::prelude::*;
 use bevy_vector_shapes::prelude::*;
-use serde::{Deserialize, Serialize};
+use serde::de::{self, Visitor};
+use serde::{Deserialize, Deserializer, Serialize};
 use std::f32::consts::TAU;
 use std::time::Duration;
 
-#[derive(Default, Reflect, Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
-pub enum BuildingType {
+/// Id of a building definition
+#[derive(Default, Reflect, Clone, PartialEq, Eq, Hash, Debug, Serialize)]
+#[serde(transparent)]
+pub struct BuildingType(String);
+
+impl<'de> Deserialize<'de> for BuildingType {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(BuildingTypeVisitor)
+    }
+}
+
+///Reads the id, a bare name of the old enum arrives without its name and is read as no building
+struct BuildingTypeVisitor;
+
+impl Visitor<'_> for BuildingTypeVisitor {
+    type Value = BuildingType;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("a building id")
+    }
+
+    fn visit_str<E: de::Error>(self, id: &str) -> Result<BuildingType, E> {
+        Ok(BuildingType::new(id))
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<BuildingType, E> {
+        Ok(BuildingType::default())
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<BuildingType, E> {
+        Ok(BuildingType::default())
+    }
+}
+
+///Variants of the building enum that came before the definition files and their ids, `None` was no building
+const LEGACY_BUILDING_NAMES: [(&str, &str); 4] = [
+    ("None", ""),
+    ("Extractor", "extractor"),
+    ("ConveyorBelt", "conveyor_belt"),
+    ("InserterType", "inserter"),
+];
+
+impl BuildingType {
+    pub fn new(id: &str) -> Self {
+        BuildingType(id.to_string())
+    }
+
+    pub fn id(&self) -> &str {
+        &self.0
+    }
+
+    pub fn from_legacy_name(name: &str) -> Option<BuildingType> {
+        LEGACY_BUILDING_NAMES
+            .iter()
+            .find(|(legacy_name, _)| *legacy_name == name)
+            .map(|(_, id)| BuildingType::new(id))
+    }
+
+    ///Older files store `building_type: Extractor` where the id is a string now. Ron hands a bare
+    ///identifier to serde without its name, so the text is converted before it is parsed.
+    ///Returns nothing when there was no old name in the text
+    pub fn migrate_legacy_ron(text: &str) -> Option<String> {
+        const FIELD: &str = "building_type:";
+        let mut migrated = String::with_capacity(text.len());
+        let mut rest = text;
+        let mut changed = false;
+        while let Some(start) = rest.find(FIELD) {
+            let (before, after) = rest.split_at(start + FIELD.len());
+            migrated.push_str(before);
+            let value = after.trim_start();
+            migrated.push_str(&after[..after.len() - value.len()]);
+            let name_length = value
+                .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                .unwrap_or(value.len());
+            rest = value;
+            if let Some(building_type) = Self::from_legacy_name(&value[..name_length]) {
+                migrated.push_str(&format!("{:?}", building_type.id()));
+                rest = &value[name_length..];
+                changed = true;
+            }
+        }
+        migrated.push_str(rest);
+        changed.then_some(migrated)
+    }
+}
+
+#[derive(Default, Reflect, Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub enum BeltTier {
     #[default]
-    None,
-    Extractor,
-    ConveyorBelt,
-    InserterType,
+    Slow,
+    Fast,
+    Express,
+}
+
+impl BeltTier {
+    pub fn speed(&self) -> f32 {
+        match self {
+            BeltTier::Slow => 2.5,
+            BeltTier::Fast => 3.75,
+            BeltTier::Express => 5.0,
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            BeltTier::Slow => YELLOW.into(),
+            BeltTier::Fast => RED.into(),
+            BeltTier::Express => DEEP_SKY_BLUE.into(),
+        }
+    }
+
+    pub fn upgraded(&self) -> Option<BeltTier> {
+        match self {
+            BeltTier::Slow => Some(BeltTier::Fast),
+            BeltTier::Fast => Some(BeltTier::Express),
+            BeltTier::Express => None,
+        }
+    }
 }
 
 #[derive(Default, Reflect, Component)]
 pub struct Preview {}
 
+///Why a building can't be placed on a cell
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlacementError {
+    Undiscovered,
+    Occupied,
+    WrongGround,
+    MissingItems,
+}
+
+impl PlacementError {
+    pub fn message(&self) -> &'static str {
+        match self {
+            PlacementError::Undiscovered => "This area is not discovered yet",
+            PlacementError::Occupied => "Something is already built here",
+            PlacementError::WrongGround => "Needs to stand on a resource deposit",
+            PlacementError::MissingItems => "Not enough items in the inventory",
+        }
+    }
+}
+
 #[derive(Default, Reflect, Component)]
 pub struct Active {}
 
+/// Grey material for the models of switched off buildings
+#[derive(Resource)]
+pub struct DisabledMaterial(pub Handle<StandardMaterial>);
+
+impl FromWorld for DisabledMaterial {
+    fn from_world(world: &mut World) -> Self {
+        let mut materials = world.resource_mut::<Assets<StandardMaterial>>();
+        DisabledMaterial(materials.add(StandardMaterial {
+            base_color: Color::srgb(0.35, 0.35, 0.35),
+            perceptual_roughness: 1.0,
+            ..default()
+        }))
+    }
+}
+
+///Material a mesh had before its building was switched off
+#[derive(Component)]
+pub struct OriginalMaterial(pub Handle<StandardMaterial>);
+
+/// What a machine is doing right now, shown above it and in the info panel
+#[derive(Component, Default, Reflect, Clone, Copy, PartialEq, Eq, Debug)]
+#[reflect(Component)]
+pub enum BuildingStatus {
+    Working,
+    ///nothing to work on
+    #[default]
+    Idle,
+    ///no space to hand the result to
+    OutputBlocked,
+    ///the ground below has nothing left to extract
+    NoResource,
+    ///there is no power grid yet, nothing runs out of power
+    #[allow(dead_code)]
+    NoPower,
+    ///switched off by the player
+    Disabled,
+}
+
+impl BuildingStatus {
+    pub fn label(&self) -> &'static str {
+        match self {
+            BuildingStatus::Working => "Working",
+            BuildingStatus::Idle => "Idle",
+            BuildingStatus::OutputBlocked => "Output blocked",
+            BuildingStatus::NoResource => "No resource",
+            BuildingStatus::NoPower => "No power",
+            BuildingStatus::Disabled => "Disabled",
+        }
+    }
+
+    pub fn icon(&self) -> &'static str {
+        match self {
+            BuildingStatus::Working => "*",
+            BuildingStatus::Idle => "z",
+            BuildingStatus::OutputBlocked => "!",
+            BuildingStatus::NoResource => "x",
+            BuildingStatus::NoPower => "~",
+            BuildingStatus::Disabled => "-",
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            BuildingStatus::Working => LIME.into(),
+            BuildingStatus::Idle => GRAY.into(),
+            BuildingStatus::OutputBlocked => ORANGE.into(),
+            BuildingStatus::NoResource => RED.into(),
+            BuildingStatus::NoPower => YELLOW.into(),
+            BuildingStatus::Disabled => DARK_GRAY.into(),
+        }
+    }
+}
+
+///Billboard above a machine showing its status
+#[derive(Component, Default)]
+pub struct StatusIcon;
+
+/// Belt line the player is currently dragging out, placed as a whole once the mouse is released
+#[derive(Resource, Default, Debug)]
+pub struct BeltLinePlan {
+    pub start: Option<GridPosition>,
+    pub pieces: Vec<(GridPosition, GridRotation)>,
+}
+
+impl BeltLinePlan {
+    pub fn clear(&mut self) {
+        self.start = None;
+        self.pieces.clear();
+    }
+
+    ///Plans an L-shaped line from start to end that runs along the longer axis first,
+    ///every piece faces the next one and a single piece keeps the given rotation
+    pub fn plan(&mut self, start: GridPosition, end: GridPosition, rotation: GridRotation) {
+        self.pieces.clear();
+        let corner = if (end.x - start.x).abs() >= (end.y - start.y).abs() {
+            GridPosition {
+                x: end.x,
+                y: start.y,
+            }
+        } else {
+            GridPosition {
+                x: start.x,
+                y: end.y,
+            }
+        };
+
+        let mut cells = vec![start];
+        for target in [corner, end] {
+            let mut current = *cells.last().unwrap();
+            while current != target {
+                current = GridPosition {
+                    x: current.x + (target.x - current.x).signum(),
+                    y: current.y + (target.y - current.y).signum(),
+                };
+                cells.push(current);
+            }
+        }
+
+        let mut grid_rotation = rotation;
+        for (i, cell) in cells.iter().enumerate() {
+            if let Some(next) = cells.get(i + 1) {
+                grid_rotation = GridRotation::towards(*cell, *next).unwrap();
+            }
+            self.pieces.push((*cell, grid_rotation));
+        }
+    }
+}
+
 #[derive(Event, Debug)]
 pub struct BuildingPlacedEvent {
     pub building_type: BuildingType,
     pub grid_position: GridPosition,
     pub grid_rotation: GridRotation,
     pub entity: Entity,
+    ///replayed from the building history or a save, so it is not recorded again
+    pub from_history: bool,
 }
 
 #[derive(Event)]
@@ -36,10 +300,44 @@ pub struct ConveyorPlacedEvent {
     pub entity: Entity,
 }
 
+#[derive(Event)]
+pub struct BuildingRotatedEvent {
+    pub building_entity: Entity,
+    pub grid_position: GridPosition,
+    pub from: GridRotation,
+    pub to: GridRotation,
+    ///replayed from the building history or a save, so it is not recorded again
+    pub from_history: bool,
+}
+
+#[derive(Event)]
+pub struct BuildingMovedEvent {
+    pub building_entity: Entity,
+    pub from: GridPosition,
+    pub to: GridPosition,
+    ///replayed from the building history or a save, so it is not recorded again
+    pub from_history: bool,
+}
+
+/// Region the player is dragging out to tear down, optionally only one type of building
+#[derive(Resource, Default, Debug)]
+pub struct DeconstructionSelection {
+    pub start: Option<GridPosition>,
+    pub filter: Option<BuildingType>,
+}
+
+/// Building that got picked up to be put down somewhere else
+#[derive(Resource, Default, Debug)]
+pub struct MoveTool {
+    pub carried: Option<(Entity, GridPosition)>,
+}
+
 #[derive(Event)]
 pub struct BuildingRemovedEvent {
     pub building_entity: Entity,
     pub grid_position: GridPosition,
+    ///replayed from the building history or a save, so it is not recorded again
+    pub from_history: bool,
 }
 
 #[derive(Component, Default, Reflect)]
@@ -49,40 +347,71 @@ pub struct Building {
 }
 
 impl Building {
+    ///Spawns the building with the model and behaviours of its definition
     pub fn spawn(
-        building_type: BuildingType,
+        definition: &BuildingDefinition,
         position: Vec3,
         rotation: Quat,
         size: f32,
         commands: &mut Commands,
         asset_server: &mut AssetServer,
         shapes: &mut ShapeCommands,
-    ) -> Option<Entity> {
-        match building_type {
-            BuildingType::None => None,
-            BuildingType::Extractor => Some(Extractor::spawn(
-                position,
-                rotation,
-                size,
-                commands,
-                asset_server,
-            )),
-            BuildingType::ConveyorBelt => Some(BeltElement::spawn(
-                position,
-                rotation,
-                size,
-                commands,
-                shapes,
-                asset_server,
-            )),
-            BuildingType::InserterType => Some(Inserter::spawn(
-                position,
-                rotation,
-                size,
-                commands,
-                asset_server,
-            )),
+    ) -> Entity {
+        let model = asset_server.load(definition.model.clone());
+        let mut building = commands.spawn((
+            Transform::from_translation(position)
+                .with_rotation(rotation)
+                .with_scale(Vec3::splat(size)),
+            Visibility::default(),
+            Building {
+                building_type: definition.building_type(),
+            },
+            Name::new(definition.name.clone()),
+            StateScoped(AppState::Game),
+        ));
+        building.with_children(|parent| {
+            parent.spawn((
+                SceneRoot(model),
+                Transform {
+                    translation: definition.model_offset,
+                    rotation: Quat::from_rotation_y(TAU * definition.model_rotation),
+                    scale: Vec3::splat(definition.scale),
+                },
+            ));
+        });
+        if !definition.required_ground.is_empty() {
+            building.insert(RequiresGround {
+                allowed_ground: definition.required_ground.clone(),
+            });
+        }
+
+        for behaviour in definition.behaviours.iter() {
+            match behaviour {
+                BuildingBehaviour::Extractor { interval } => {
+                    building.insert((
+                        Extractor {
+                            timer: Timer::new(Duration::from_secs_f32(*interval), Repeating),
+                        },
+                        BuildingStatus::default(),
+                    ));
+                }
+                BuildingBehaviour::ConveyorBelt { tier } => {
+                    BeltElement::insert(&mut building, *tier, shapes);
+                }
+                BuildingBehaviour::Inserter => {
+                    building.insert((Inserter::default(), BuildingStatus::default()));
+                }
+                BuildingBehaviour::Lab { interval } => {
+                    building.insert((
+                        Lab {
+                            timer: Timer::new(Duration::from_secs_f32(*interval), Repeating),
+                        },
+                        BuildingStatus::default(),
+                    ));
+                }
+            }
         }
+        building.id()
     }
 }
 
@@ -96,87 +425,28 @@ pub struct Extractor {
     pub timer: Timer,
 }
 
-impl Extractor {
-    pub fn spawn(
-        position: Vec3,
-        rotation: Quat,
-        size: f32,
-        commands: &mut Commands,
-        asset_server: &mut AssetServer,
-    ) -> Entity {
-        let model = asset_server.load("models/extractor.glb#Scene0");
-        commands
-            .spawn((
-                SceneRoot(model),
-                Transform::from_translation(position)
-                    .with_rotation(rotation)
-                    .with_scale(Vec3::splat(size)),
-                Building {
-                    building_type: BuildingType::Extractor,
-                },
-                Extractor {
-                    timer: Timer::new(Duration::from_secs_f32(0.50), Repeating),
-                },
-                RequiresGround {
-                    allowed_ground: vec![
-                        GroundLayerType::BloodResource,
-                        GroundLayerType::BlackBileResource,
-                        GroundLayerType::PhlegmResource,
-                        GroundLayerType::YellowBileResource,
-                    ],
-                },
-            ))
-            .id()
-    }
+/// Takes items from the belts that end at it and hands them to the current research
+#[derive(Component, Default, Reflect)]
+pub struct Lab {
+    pub timer: Timer,
 }
 
 #[derive(Component, Default, Reflect, Debug)]
 #[reflect(Component)]
 pub struct BeltElement {
-    pub speed: f32,
+    pub tier: BeltTier,
     pub conveyor_belt: Option<Entity>,
     // pub item: Option<Entity>,
     // pub item_reached_center: bool,
 }
 
 impl BeltElement {
-    pub fn spawn(
-        position: Vec3,
-        rotation: Quat,
-        size: f32,
-        commands: &mut Commands,
-        shapes: &mut ShapeCommands,
-        asset_server: &mut AssetServer,
-    ) -> Entity {
-        let model = asset_server.load("models/conveyor-bars-stripe.glb#Scene0");
-
-        let entity = commands
-            .spawn((
-                SpatialBundle {
-                    transform: Transform::from_translation(position)
-                        .with_rotation(rotation)
-                        .with_scale(Vec3::splat(size)),
-                    ..default()
-                },
-                BeltElement {
-                    conveyor_belt: None,
-                    speed: 1.0,
-                    ..default()
-                },
-                Building {
-                    building_type: BuildingType::ConveyorBelt,
-                },
-                Name::new("Belt Piece"),
-            ))
-            .with_children(|parent| {
-                parent.spawn((
-                    SceneRoot(model),
-                    Transform {
-                        translation: Vec3::Y * -0.05,
-                        rotation: Quat::from_rotation_y(TAU * 0.25),
-                        ..default()
-                    },
-                ));
+    ///Turns the building into a belt piece with the arrows of its tier
+    pub fn insert(building: &mut EntityCommands, tier: BeltTier, shapes: &ShapeCommands) {
+        building
+            .insert(BeltElement {
+                conveyor_belt: None,
+                tier,
             })
             .with_shape_children(&shapes.config(), |shapes| {
                 shapes.hollow = true;
@@ -187,16 +457,24 @@ impl BeltElement {
                 )
                 .with_translation(Vec3::Y * 0.25);
                 shapes.thickness = 0.01;
-                shapes.color = YELLOW.into();
-                shapes.ngon(3.0, 0.2);
+                shapes.color = tier.color();
+                shapes.ngon(3.0, 0.2).insert(BeltArrow);
                 shapes.translate(Vec3::Y * -0.15);
-                shapes.rect(Vec2::new(0.1, 0.3));
-            })
-            .id();
-        entity
+                shapes.rect(Vec2::new(0.1, 0.3)).insert(BeltArrow);
+            });
     }
 }
 
+#[derive(Component, Default, Reflect)]
+pub struct BeltArrow;
+
+#[derive(Event)]
+pub struct BeltUpgradedEvent {
+    pub building_entity: Entity,
+    pub building_type: BuildingType,
+    pub tier: BeltTier,
+}
+
 #[derive(Component, Default, Reflect)]
 #[reflect(Component)]
 pub struct Inserter {
@@ -205,35 +483,6 @@ pub struct Inserter {
     pub target_reached: bool,
 }
 
-impl Inserter {
-    pub fn spawn(
-        position: Vec3,
-        rotation: Quat,
-        size: f32,
-        commands: &mut Commands,
-        asset_server: &mut AssetServer,
-        // shapes: &mut ShapeCommands,
-    ) -> Entity {
-        let model = asset_server.load("models/robot-arm-a.glb#Scene0");
-        let entity = commands
-            .spawn((
-                SceneRoot(model),
-                Transform {
-                    translation: position,
-                    rotation,
-                    scale: Vec3::splat(size),
-                },
-                Inserter { ..default() },
-                Building {
-                    building_type: BuildingType::InserterType,
-                },
-                Name::new("Inserter"),
-            ))
-            .id();
-        entity
-    }
-}
-
 #[derive(Debug, Reflect, Clone)]
 pub struct ConveyorSegment {
     start_position: Vec3,
@@ -241,6 +490,7 @@ pub struct ConveyorSegment {
     direction: Dir3,
     length: f32,
     pub is_connector: bool,
+    pub tier: BeltTier,
     rect: Rect,
 }
 
@@ -272,11 +522,19 @@ impl ConveyorSegment {
     }
 
     pub fn set_start_position(&mut self, start_position: Vec3) {
-        *self = Self::new(start_position, self.end_position);
+        *self = Self {
+            is_connector: self.is_connector,
+            tier: self.tier,
+            ..Self::new(start_position, self.end_position)
+        };
     }
 
     pub fn set_end_position(&mut self, end_position: Vec3) {
-        *self = Self::new(self.start_position, end_position);
+        *self = Self {
+            is_connector: self.is_connector,
+            tier: self.tier,
+            ..Self::new(self.start_position, end_position)
+        };
     }
 
     pub fn new(start_position: Vec3, end_position: Vec3) -> Self {
@@ -297,6 +555,7 @@ impl ConveyorSegment {
             length,
             direction,
             is_connector: false,
+            tier: BeltTier::default(),
             rect: Rect::from_center_size(center.xz(), size),
         }
     }
@@ -362,14 +621,32 @@ impl ConveyorSegment {
     pub fn position_for_progress(&self, progress: f32) -> Vec3 {
         self.start_position.lerp(self.end_position, progress)
     }
+
+    ///Distance of the point from the start of the segment, without rounding
+    pub fn distance_for_point(&self, point: Vec3) -> f32 {
+        (point - self.start_position)
+            .dot(*self.direction)
+            .clamp(0.0, self.length)
+    }
+}
+
+#[derive(Default, Reflect, Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub enum ItemType {
+    #[default]
+    YellowBile,
+}
+
+impl ItemType {
+    pub fn color(&self) -> Color {
+        match self {
+            ItemType::YellowBile => YELLOW.into(),
+        }
+    }
 }
 
 #[derive(Reflect, Debug, Clone)]
 pub struct BeltItem {
-    pub item_entity: Entity,
-    pub position: Vec3,
-    pub segment_progress: f32,
-    pub segment_index: usize,
+    pub item_type: ItemType,
     pub item_width: f32,
 }
 
@@ -378,6 +655,7 @@ pub struct BeltPiece {
     pub entity: Entity,
     pub grid_rotation: GridRotation,
     pub grid_position: GridPosition,
+    pub tier: BeltTier,
 }
 
 impl BeltPiece {
@@ -399,3 +677,96 @@ impl BeltPiece {
         self.grid_position.get_relative_right(self.grid_rotation)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn position(x: i32, y: i32) -> GridPosition {
+        GridPosition { x, y }
+    }
+
+    #[test]
+    fn legacy_building_names_become_ids() {
+        let text = "PlacedBuilding(\n    building_type: ConveyorBelt,\n),\nBlueprintEntry(building_type:InserterType, settings: (active: true))";
+        assert_eq!(
+            BuildingType::migrate_legacy_ron(text).unwrap(),
+            "PlacedBuilding(\n    building_type: \"conveyor_belt\",\n),\nBlueprintEntry(building_type:\"inserter\", settings: (active: true))"
+        );
+        assert_eq!(
+            BuildingType::migrate_legacy_ron("building_type: None"),
+            Some("building_type: \"\"".to_string())
+        );
+    }
+
+    #[test]
+    fn legacy_names_load_as_no_building_until_converted() {
+        let text = "[\"lab\", Extractor, None]";
+        let building_types = ron::de::from_str::<Vec<BuildingType>>(text).unwrap();
+        assert_eq!(
+            building_types,
+            vec![
+                BuildingType::new("lab"),
+                BuildingType::default(),
+                BuildingType::default()
+            ]
+        );
+    }
+
+    #[test]
+    fn current_ids_are_not_migrated() {
+        assert_eq!(
+            BuildingType::migrate_legacy_ron("(building_type: \"lab\", name: \"Lab\")"),
+            None
+        );
+        assert_eq!(BuildingType::from_legacy_name("Windmill"), None);
+        //the lab came with the definition files, it never had an enum name
+        assert_eq!(BuildingType::from_legacy_name("Lab"), None);
+    }
+
+    #[test]
+    fn single_piece_keeps_the_rotation() {
+        let mut plan = BeltLinePlan::default();
+        plan.plan(position(2, 3), position(2, 3), GridRotation::E);
+        assert_eq!(plan.pieces, vec![(position(2, 3), GridRotation::E)]);
+    }
+
+    #[test]
+    fn straight_line_faces_the_end() {
+        let mut plan = BeltLinePlan::default();
+        plan.plan(position(0, 0), position(0, -3), GridRotation::N);
+        assert_eq!(plan.pieces.len(), 4);
+        assert!(plan
+            .pieces
+            .iter()
+            .all(|(_, rotation)| *rotation == GridRotation::S));
+        assert_eq!(plan.pieces.last().unwrap().0, position(0, -3));
+    }
+
+    #[test]
+    fn line_runs_along_the_longer_axis_first() {
+        let mut plan = BeltLinePlan::default();
+        plan.plan(position(0, 0), position(3, 1), GridRotation::N);
+        assert_eq!(
+            plan.pieces,
+            vec![
+                (position(0, 0), GridRotation::W),
+                (position(1, 0), GridRotation::W),
+                (position(2, 0), GridRotation::W),
+                (position(3, 0), GridRotation::N),
+                (position(3, 1), GridRotation::N),
+            ]
+        );
+
+        plan.plan(position(0, 0), position(-1, 2), GridRotation::N);
+        assert_eq!(
+            plan.pieces,
+            vec![
+                (position(0, 0), GridRotation::N),
+                (position(0, 1), GridRotation::N),
+                (position(0, 2), GridRotation::E),
+                (position(-1, 2), GridRotation::E),
+            ]
+        );
+    }
+}
@@ -0,0 +1,170 @@
+use crate::building::building_components::{BeltTier, BuildingType, ItemType};
+use crate::world_grid::world_gird_components::GroundLayerType;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use serde::Deserialize;
+use std::path::Path;
+
+/// Components that give a building its behaviour
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub enum BuildingBehaviour {
+    ///takes an item out of the node below every interval seconds
+    Extractor {
+        interval: f32,
+    },
+    ConveyorBelt {
+        tier: BeltTier,
+    },
+    Inserter,
+    ///hands an item to the current research every interval seconds
+    Lab {
+        interval: f32,
+    },
+}
+
+fn default_scale() -> f32 {
+    1.0
+}
+
+fn default_footprint() -> UVec2 {
+    UVec2::ONE
+}
+
+/// A building as the designers describe it in `assets/buildings/*.ron`
+#[derive(Deserialize, Debug, Clone)]
+pub struct BuildingDefinition {
+    pub id: String,
+    pub name: String,
+    pub model: String,
+    ///scale of the model inside its cell
+    #[serde(default = "default_scale")]
+    pub scale: f32,
+    #[serde(default)]
+    pub model_offset: Vec3,
+    ///rotation of the model around the up axis in turns
+    #[serde(default)]
+    pub model_rotation: f32,
+    ///cells the building covers, the grid only supports single cells for now
+    #[serde(default = "default_footprint")]
+    pub footprint: UVec2,
+    ///ground the building has to stand on, any ground works if it is empty
+    #[serde(default)]
+    pub required_ground: Vec<GroundLayerType>,
+    #[serde(default)]
+    pub behaviours: Vec<BuildingBehaviour>,
+    ///items taken from the inventory to build it, they are given back on deconstruction
+    #[serde(default)]
+    pub cost: Vec<(ItemType, u32)>,
+    ///belts lie flat on the ground and can be walked over, other buildings block the player
+    #[serde(default)]
+    pub blocks_movement: bool,
+    ///position in the build menu, lower comes first
+    #[serde(default)]
+    pub menu_order: i32,
+}
+
+impl BuildingDefinition {
+    pub fn building_type(&self) -> BuildingType {
+        BuildingType::new(&self.id)
+    }
+
+    pub fn belt_tier(&self) -> Option<BeltTier> {
+        self.behaviours
+            .iter()
+            .find_map(|behaviour| match behaviour {
+                BuildingBehaviour::ConveyorBelt { tier } => Some(*tier),
+                _ => None,
+            })
+    }
+}
+
+/// All building definitions by their id, read once at startup
+#[derive(Resource, Default, Debug)]
+pub struct BuildingDefinitions {
+    definitions: HashMap<BuildingType, BuildingDefinition>,
+}
+
+impl BuildingDefinitions {
+    ///Reads every ron file in the folder, broken definitions are skipped
+    pub fn load(folder: &Path) -> Self {
+        let mut definitions = HashMap::new();
+        let entries = match std::fs::read_dir(folder) {
+            Ok(entries) => entries,
+            Err(e) => {
+                error!("could not read the building definitions {:?}", e);
+                return Self { definitions };
+            }
+        };
+        for path in entries.filter_map(|entry| entry.ok().map(|entry| entry.path())) {
+            if path.extension().is_none_or(|extension| extension != "ron") {
+                continue;
+            }
+            let definition = match std::fs::read_to_string(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|text| {
+                    ron::de::from_str::<BuildingDefinition>(&text).map_err(|e| e.to_string())
+                }) {
+                Ok(definition) => definition,
+                Err(e) => {
+                    error!("could not load building definition {:?}: {}", path, e);
+                    continue;
+                }
+            };
+            if definition.footprint != UVec2::ONE {
+                error!(
+                    "{} covers more than one cell, the grid only supports single cell buildings",
+                    definition.id
+                );
+                continue;
+            }
+            info!("loaded building definition {}", definition.id);
+            definitions.insert(definition.building_type(), definition);
+        }
+        Self { definitions }
+    }
+
+    pub fn get(&self, building_type: &BuildingType) -> Option<&BuildingDefinition> {
+        self.definitions.get(building_type)
+    }
+
+    ///Definitions in the order of the build menu
+    pub fn sorted(&self) -> Vec<&BuildingDefinition> {
+        let mut definitions = self.definitions.values().collect::<Vec<_>>();
+        definitions.sort_by(|a, b| a.menu_order.cmp(&b.menu_order).then(a.id.cmp(&b.id)));
+        definitions
+    }
+
+    pub fn cost(&self, building_type: &BuildingType) -> Vec<(ItemType, u32)> {
+        self.get(building_type)
+            .map(|definition| definition.cost.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn belt_tier(&self, building_type: &BuildingType) -> Option<BeltTier> {
+        self.get(building_type)
+            .and_then(|definition| definition.belt_tier())
+    }
+
+    pub fn is_conveyor_belt(&self, building_type: &BuildingType) -> bool {
+        self.belt_tier(building_type).is_some()
+    }
+
+    ///The belt building of a tier
+    pub fn belt(&self, tier: BeltTier) -> Option<BuildingType> {
+        self.sorted()
+            .into_iter()
+            .find(|definition| definition.belt_tier() == Some(tier))
+            .map(|definition| definition.building_type())
+    }
+
+    pub fn blocks_movement(&self, building_type: &BuildingType) -> bool {
+        self.get(building_type)
+            .is_some_and(|definition| definition.blocks_movement)
+    }
+
+    pub fn required_ground(&self, building_type: &BuildingType) -> Option<&Vec<GroundLayerType>> {
+        self.get(building_type)
+            .map(|definition| &definition.required_ground)
+            .filter(|required_ground| !required_ground.is_empty())
+    }
+}
@@ -0,0 +1,265 @@
+use crate::building::building_components::BuildingType;
+use crate::world_grid::world_gird_components::{GridPosition, GridRotation};
+use bevy::prelude::*;
+
+const MAX_HISTORY_STEPS: usize = 100;
+
+/// A single reversible change to the buildings on the grid
+#[derive(Debug, Clone)]
+pub enum BuildingOperation {
+    Placed {
+        building_type: BuildingType,
+        grid_position: GridPosition,
+        grid_rotation: GridRotation,
+        active: bool,
+    },
+    ///the building comes back switched off if it was switched off
+    Removed {
+        building_type: BuildingType,
+        grid_position: GridPosition,
+        grid_rotation: GridRotation,
+        active: bool,
+    },
+    Rotated {
+        building_type: BuildingType,
+        grid_position: GridPosition,
+        from: GridRotation,
+        to: GridRotation,
+    },
+    Moved {
+        building_type: BuildingType,
+        from: GridPosition,
+        to: GridPosition,
+    },
+}
+
+impl BuildingOperation {
+    ///The operation that reverts this one
+    pub fn inverse(&self) -> Self {
+        match self.clone() {
+            BuildingOperation::Placed {
+                building_type,
+                grid_position,
+                grid_rotation,
+                active,
+            } => BuildingOperation::Removed {
+                building_type,
+                grid_position,
+                grid_rotation,
+                active,
+            },
+            BuildingOperation::Removed {
+                building_type,
+                grid_position,
+                grid_rotation,
+                active,
+            } => BuildingOperation::Placed {
+                building_type,
+                grid_position,
+                grid_rotation,
+                active,
+            },
+            BuildingOperation::Rotated {
+                building_type,
+                grid_position,
+                from,
+                to,
+            } => BuildingOperation::Rotated {
+                building_type,
+                grid_position,
+                from: to,
+                to: from,
+            },
+            BuildingOperation::Moved {
+                building_type,
+                from,
+                to,
+            } => BuildingOperation::Moved {
+                building_type,
+                from: to,
+                to: from,
+            },
+        }
+    }
+}
+
+/// Undo and redo stacks of building operations, one step can hold many operations
+#[derive(Resource, Default, Debug)]
+pub struct BuildingHistory {
+    undo_steps: Vec<Vec<BuildingOperation>>,
+    redo_steps: Vec<Vec<BuildingOperation>>,
+    current_step: Vec<BuildingOperation>,
+}
+
+impl BuildingHistory {
+    pub fn record(&mut self, operation: BuildingOperation) {
+        self.current_step.push(operation);
+        self.redo_steps.clear();
+    }
+
+    ///Closes the step that is being recorded, the next operation starts a new one
+    pub fn finish_step(&mut self) {
+        if self.current_step.is_empty() {
+            return;
+        }
+        let step = std::mem::take(&mut self.current_step);
+        self.undo_steps.push(step);
+        if self.undo_steps.len() > MAX_HISTORY_STEPS {
+            self.undo_steps.remove(0);
+        }
+    }
+
+    ///Operations that revert the last step, in the order they have to be applied
+    pub fn undo(&mut self) -> Option<Vec<BuildingOperation>> {
+        self.finish_step();
+        let step = self.undo_steps.pop()?;
+        let operations = step.iter().rev().map(|o| o.inverse()).collect();
+        self.redo_steps.push(step);
+        Some(operations)
+    }
+
+    ///Operations that repeat the last undone step
+    pub fn redo(&mut self) -> Option<Vec<BuildingOperation>> {
+        self.finish_step();
+        let step = self.redo_steps.pop()?;
+        let operations = step.clone();
+        self.undo_steps.push(step);
+        Some(operations)
+    }
+
+    pub fn clear(&mut self) {
+        self.undo_steps.clear();
+        self.redo_steps.clear();
+        self.current_step.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn placed(x: i32) -> BuildingOperation {
+        BuildingOperation::Placed {
+            building_type: BuildingType::new("extractor"),
+            grid_position: GridPosition { x, y: 0 },
+            grid_rotation: GridRotation::N,
+            active: true,
+        }
+    }
+
+    fn placed_at(operation: &BuildingOperation) -> Option<i32> {
+        match operation {
+            BuildingOperation::Placed { grid_position, .. } => Some(grid_position.x),
+            _ => None,
+        }
+    }
+
+    fn removed_at(operation: &BuildingOperation) -> Option<i32> {
+        match operation {
+            BuildingOperation::Removed { grid_position, .. } => Some(grid_position.x),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn undo_reverts_a_whole_step_in_reverse() {
+        let mut history = BuildingHistory::default();
+        history.record(placed(1));
+        history.record(placed(2));
+        history.finish_step();
+        history.record(placed(3));
+
+        let undone = history.undo().unwrap();
+        assert_eq!(
+            undone.iter().map(removed_at).collect::<Vec<_>>(),
+            vec![Some(3)]
+        );
+        let undone = history.undo().unwrap();
+        assert_eq!(
+            undone.iter().map(removed_at).collect::<Vec<_>>(),
+            vec![Some(2), Some(1)]
+        );
+        assert!(history.undo().is_none());
+    }
+
+    #[test]
+    fn redo_repeats_the_undone_step() {
+        let mut history = BuildingHistory::default();
+        history.record(placed(1));
+        history.record(placed(2));
+        history.undo().unwrap();
+
+        let redone = history.redo().unwrap();
+        assert_eq!(
+            redone.iter().map(placed_at).collect::<Vec<_>>(),
+            vec![Some(1), Some(2)]
+        );
+        assert!(history.redo().is_none());
+        assert!(history.undo().is_some());
+    }
+
+    #[test]
+    fn recording_drops_the_redo_steps() {
+        let mut history = BuildingHistory::default();
+        history.record(placed(1));
+        history.undo().unwrap();
+        history.record(placed(2));
+        assert!(history.redo().is_none());
+    }
+
+    #[test]
+    fn only_the_latest_steps_are_kept() {
+        let mut history = BuildingHistory::default();
+        for x in 0..MAX_HISTORY_STEPS as i32 + 5 {
+            history.record(placed(x));
+            history.finish_step();
+        }
+        let mut steps = 0;
+        while history.undo().is_some() {
+            steps += 1;
+        }
+        assert_eq!(steps, MAX_HISTORY_STEPS);
+    }
+
+    #[test]
+    fn undoing_a_removal_keeps_the_building_switched_off() {
+        let mut history = BuildingHistory::default();
+        history.record(BuildingOperation::Removed {
+            building_type: BuildingType::new("extractor"),
+            grid_position: GridPosition { x: 0, y: 0 },
+            grid_rotation: GridRotation::N,
+            active: false,
+        });
+        let undone = history.undo().unwrap();
+        assert!(matches!(
+            undone[..],
+            [BuildingOperation::Placed { active: false, .. }]
+        ));
+    }
+
+    #[test]
+    fn inverse_swaps_rotation_and_move() {
+        let building_type = BuildingType::new("conveyor_belt");
+        let rotated = BuildingOperation::Rotated {
+            building_type: building_type.clone(),
+            grid_position: GridPosition { x: 0, y: 0 },
+            from: GridRotation::N,
+            to: GridRotation::E,
+        };
+        let BuildingOperation::Rotated { from, to, .. } = rotated.inverse() else {
+            panic!("a rotation reverts to a rotation");
+        };
+        assert_eq!((from, to), (GridRotation::E, GridRotation::N));
+
+        let moved = BuildingOperation::Moved {
+            building_type,
+            from: GridPosition { x: 0, y: 0 },
+            to: GridPosition { x: 2, y: 1 },
+        };
+        let BuildingOperation::Moved { from, to, .. } = moved.inverse() else {
+            panic!("a move reverts to a move");
+        };
+        assert_eq!(from, GridPosition { x: 2, y: 1 });
+        assert_eq!(to, GridPosition { x: 0, y: 0 });
+    }
+}
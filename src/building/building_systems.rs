@@ -1,10 +1,20 @@
+use crate::blueprint::blueprint_components::{BlueprintMode, BlueprintTool};
 use crate::building::building_components::*;
-use crate::building::conveyor_belt::{ConveyorBelt, ConveyorSegmentsChanged};
-use crate::player::player_components::GameCursor;
+use crate::building::building_definitions::BuildingDefinitions;
+use crate::building::building_history::{BuildingHistory, BuildingOperation};
+use crate::building::conveyor_belt::{BeltItemBatches, ConveyorBelt, ConveyorSegmentsChanged};
+use crate::building::conveyor_graph::ConveyorGraph;
+use crate::general::general_components::GeneralAssets;
+use crate::player::player_components::{GameCursor, Inventory};
+use crate::research::research_components::{Research, TechTree, TechTreeHandle};
+use crate::statistics::statistics_components::{ItemConsumedEvent, ItemProducedEvent};
 use crate::utilities::utility_methods::find_child_with_name;
-use crate::world_grid::components::yellow_bile::YellowBileItem;
+use crate::world_grid::components::yellow_bile::{mine_resource_node, YellowBileResource};
 use crate::world_grid::world_gird_components::*;
+use avian3d::prelude::{Collider, RigidBody};
 use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy_mod_billboard::prelude::*;
 use bevy_vector_shapes::prelude::*;
 use std::f32::consts::TAU;
 
@@ -15,9 +25,11 @@ pub fn place_building_system(
     input: Res<ButtonInput<MouseButton>>,
     game_cursor: ResMut<GameCursor>,
     mut world_grid: ResMut<WorldGrid>,
-    mut building_q: Query<(Entity, &Transform, &Building)>,
-    requires_ground_q: Query<&RequiresGround>,
+    mut inventory: ResMut<Inventory>,
+    definitions: Res<BuildingDefinitions>,
+    building_q: Query<(&Transform, &Building)>,
     mut building_placed_event: EventWriter<BuildingPlacedEvent>,
+    mut item_consumed_event: EventWriter<ItemConsumedEvent>,
 ) {
     if game_cursor.world_position.is_none() {
         return;
@@ -28,78 +40,536 @@ pub fn place_building_system(
     if !input.pressed(MouseButton::Left) {
         return;
     }
-    let grid_size = world_grid.grid_size;
     let position = game_cursor.world_position.unwrap();
 
     let grid_position = world_grid.grid_position_from_world_position(position);
+
+    let preview_entity = game_cursor.preview_entity.unwrap();
+
+    let Ok((transform, building)) = building_q.get(preview_entity) else {
+        return;
+    };
+    //belts are placed as whole lines by dragging
+    if definitions.is_conveyor_belt(&building.building_type) {
+        return;
+    }
+    if !can_place_building(
+        &world_grid,
+        &definitions,
+        &building.building_type,
+        grid_position,
+    ) {
+        return;
+    }
+    let cost = definitions.cost(&building.building_type);
+    if !inventory.can_afford(&cost) {
+        return;
+    }
+
+    if spawn_building_on_grid(
+        building.building_type.clone(),
+        grid_position,
+        transform.grid_rotation(),
+        true,
+        false,
+        &definitions,
+        &mut commands,
+        &mut asset_server,
+        &mut shapes,
+        &mut world_grid,
+        &mut building_placed_event,
+    )
+    .is_some()
+    {
+        inventory.spend(&cost);
+        item_consumed_event.send_batch(ItemConsumedEvent::for_cost(&cost));
+    }
+}
+
+///Checks that the cell is discovered, free and has the ground the building needs
+pub fn validate_placement(
+    world_grid: &WorldGrid,
+    definitions: &BuildingDefinitions,
+    building_type: &BuildingType,
+    grid_position: GridPosition,
+) -> Result<(), PlacementError> {
+    let Some(cell) = world_grid.get_cell(&grid_position) else {
+        return Err(PlacementError::Undiscovered);
+    };
+    if cell.surface_layer != SurfaceLayer::Empty {
+        return Err(PlacementError::Occupied);
+    }
+    if let Some(allowed_ground) = definitions.required_ground(building_type) {
+        if !allowed_ground.contains(&cell.ground_layer) {
+            return Err(PlacementError::WrongGround);
+        }
+    }
+    Ok(())
+}
+
+pub fn can_place_building(
+    world_grid: &WorldGrid,
+    definitions: &BuildingDefinitions,
+    building_type: &BuildingType,
+    grid_position: GridPosition,
+) -> bool {
+    validate_placement(world_grid, definitions, building_type, grid_position).is_ok()
+}
+
+///Spawns a building on the grid cell and announces its placement
+#[allow(clippy::too_many_arguments)]
+pub fn spawn_building_on_grid(
+    building_type: BuildingType,
+    grid_position: GridPosition,
+    grid_rotation: GridRotation,
+    active: bool,
+    from_history: bool,
+    definitions: &BuildingDefinitions,
+    commands: &mut Commands,
+    asset_server: &mut AssetServer,
+    shapes: &mut ShapeCommands,
+    world_grid: &mut WorldGrid,
+    building_placed_event: &mut EventWriter<BuildingPlacedEvent>,
+) -> Option<Entity> {
+    let definition = definitions.get(&building_type)?;
+    let grid_size = world_grid.grid_size;
     let building_position = world_grid.grid_to_world(&grid_position);
+    let cell = world_grid.cells.get_mut(&grid_position)?;
 
-    if let Some(cell) = world_grid.cells.get_mut(&grid_position) {
-        if cell.surface_layer != SurfaceLayer::Empty {
-            return;
-        };
+    let entity = Building::spawn(
+        definition,
+        building_position,
+        grid_rotation.to_quat(),
+        grid_size,
+        commands,
+        asset_server,
+        shapes,
+    );
+    if active {
+        commands.entity(entity).insert(Active {});
+    }
 
-        let preview_entity = game_cursor.preview_entity.unwrap();
+    cell.surface_layer = SurfaceLayer::Building { entity };
 
-        if building_q.get(preview_entity).is_err() {
-            return;
-        };
-        let (entity, transform, building) = building_q.get_mut(preview_entity).unwrap();
-        if let Ok(requires_ground) = requires_ground_q.get(entity) {
-            if !requires_ground.allowed_ground.contains(&cell.ground_layer) {
-                return;
-            }
+    building_placed_event.send(BuildingPlacedEvent {
+        entity,
+        building_type,
+        grid_position,
+        grid_rotation,
+        from_history,
+    });
+    Some(entity)
+}
+
+pub fn add_building_collider_system(
+    mut commands: Commands,
+    definitions: Res<BuildingDefinitions>,
+    mut building_placed_event: EventReader<BuildingPlacedEvent>,
+) {
+    for event in building_placed_event.read() {
+        if !definitions.blocks_movement(&event.building_type) {
+            continue;
         }
+        //the collider is scaled with the building to fill most of its cell,
+        //the building may already be removed again in the same frame
+        commands
+            .entity(event.entity)
+            .try_insert((RigidBody::Static, Collider::cuboid(0.9, 2.0, 0.9)));
+    }
+}
+
+pub fn belt_line_drag_system(
+    mut commands: Commands,
+    mut shapes: ShapeCommands,
+    mut asset_server: ResMut<AssetServer>,
+    input: Res<ButtonInput<MouseButton>>,
+    game_cursor: Res<GameCursor>,
+    mut world_grid: ResMut<WorldGrid>,
+    mut belt_line_plan: ResMut<BeltLinePlan>,
+    mut inventory: ResMut<Inventory>,
+    definitions: Res<BuildingDefinitions>,
+    preview_q: Query<(&Transform, &Building), With<Preview>>,
+    mut building_placed_event: EventWriter<BuildingPlacedEvent>,
+    mut conveyor_placed_event: EventWriter<ConveyorPlacedEvent>,
+    mut item_consumed_event: EventWriter<ItemConsumedEvent>,
+) {
+    let preview = game_cursor
+        .preview_entity
+        .and_then(|entity| preview_q.get(entity).ok());
+    let Some((preview_transform, preview_building)) =
+        preview.filter(|(_, building)| definitions.is_conveyor_belt(&building.building_type))
+    else {
+        belt_line_plan.clear();
+        return;
+    };
+    if input.just_pressed(MouseButton::Right) {
+        belt_line_plan.clear();
+        return;
+    }
+    let cursor_grid_position = game_cursor
+        .world_position
+        .map(|position| world_grid.grid_position_from_world_position(position));
+    if input.just_pressed(MouseButton::Left) {
+        belt_line_plan.start = cursor_grid_position;
+    }
+    let Some(start) = belt_line_plan.start else {
+        return;
+    };
+    if let Some(end) = cursor_grid_position {
+        belt_line_plan.plan(start, end, preview_transform.grid_rotation());
+    }
+    if !input.just_released(MouseButton::Left) {
+        return;
+    }
 
-        let placed_building = Building::spawn(
-            building.building_type,
-            building_position,
-            transform.rotation,
-            grid_size,
+    let building_type = &preview_building.building_type;
+    let tier = definitions.belt_tier(building_type).unwrap();
+    let planned_pieces = std::mem::take(&mut belt_line_plan.pieces);
+    belt_line_plan.clear();
+
+    //the pieces of the line form one conveyor, occupied cells split it up
+    let cost = definitions.cost(building_type);
+    let mut unaffordable = 0;
+    let mut lines: Vec<Vec<BeltPiece>> = vec![vec![]];
+    for (grid_position, grid_rotation) in planned_pieces {
+        if !can_place_building(&world_grid, &definitions, building_type, grid_position) {
+            lines.push(vec![]);
+            continue;
+        }
+        if !inventory.spend(&cost) {
+            unaffordable += 1;
+            lines.push(vec![]);
+            continue;
+        }
+        let Some(entity) = spawn_building_on_grid(
+            building_type.clone(),
+            grid_position,
+            grid_rotation,
+            true,
+            false,
+            &definitions,
             &mut commands,
             &mut asset_server,
             &mut shapes,
-        );
-        commands.entity(placed_building.unwrap()).insert(Active {});
-
-        cell.surface_layer = SurfaceLayer::Building {
-            entity: placed_building.unwrap(),
+            &mut world_grid,
+            &mut building_placed_event,
+        ) else {
+            inventory.refund(&cost);
+            continue;
         };
-
-        building_placed_event.send(BuildingPlacedEvent {
-            entity: placed_building.unwrap(),
-            building_type: building.building_type,
+        item_consumed_event.send_batch(ItemConsumedEvent::for_cost(&cost));
+        lines.last_mut().unwrap().push(BeltPiece {
+            entity,
+            grid_rotation,
             grid_position,
-            grid_rotation: transform.grid_rotation(),
+            tier,
         });
     }
+
+    if unaffordable > 0 {
+        warn!("not enough items for {} belt pieces", unaffordable);
+    }
+
+    for pieces in lines.into_iter().filter(|pieces| !pieces.is_empty()) {
+        let conveyor_belt = ConveyorBelt::spawn_with_pieces(&mut commands, pieces.clone(), vec![]);
+        for piece in pieces.iter() {
+            commands.entity(piece.entity).insert(BeltElement {
+                tier,
+                conveyor_belt: Some(conveyor_belt),
+            });
+        }
+        conveyor_placed_event.send(ConveyorPlacedEvent {
+            entity: conveyor_belt,
+        });
+    }
+}
+
+pub fn draw_belt_line_plan_system(
+    mut painter: ShapePainter,
+    belt_line_plan: Res<BeltLinePlan>,
+    world_grid: Res<WorldGrid>,
+) {
+    if belt_line_plan.pieces.is_empty() {
+        return;
+    }
+    let ghost_size = world_grid.grid_size * 0.9;
+    for (grid_position, grid_rotation) in belt_line_plan.pieces.iter() {
+        let free = world_grid
+            .get_cell(grid_position)
+            .is_some_and(|cell| cell.surface_layer == SurfaceLayer::Empty);
+        let color = if free {
+            Color::srgba(0.2, 0.8, 0.3, 0.35)
+        } else {
+            Color::srgba(0.9, 0.2, 0.2, 0.35)
+        };
+        let position = world_grid.grid_to_world(grid_position) + Vec3::Y * 0.12;
+
+        painter.hollow = false;
+        painter.color = color;
+        painter.transform =
+            Transform::from_translation(position).with_rotation(Quat::from_rotation_x(TAU * 0.25));
+        painter.rect(Vec2::splat(ghost_size));
+
+        painter.transform = Transform::IDENTITY;
+        painter.thickness = 0.04;
+        painter.color = color.with_alpha(0.9);
+        painter.line(
+            position - grid_rotation.get_direction() * ghost_size * 0.3,
+            position + grid_rotation.get_direction() * ghost_size * 0.3,
+        );
+    }
 }
 
 pub fn remove_building_system(
     input: Res<ButtonInput<MouseButton>>,
+    keys: Res<ButtonInput<KeyCode>>,
     game_cursor: ResMut<GameCursor>,
-    mut world_grid: ResMut<WorldGrid>,
+    world_grid: Res<WorldGrid>,
+    mut selection: ResMut<DeconstructionSelection>,
+    building_q: Query<&Building>,
     mut building_removed_event: EventWriter<BuildingRemovedEvent>,
 ) {
     if game_cursor.world_position.is_none() {
         return;
     };
+    let position = game_cursor.world_position.unwrap();
+    let grid_position = world_grid.grid_position_from_world_position(position);
 
-    if !input.just_pressed(MouseButton::Right) {
+    if input.just_pressed(MouseButton::Right) {
+        selection.start = Some(grid_position);
+        //with shift held only buildings of the same type as the one under the cursor go
+        selection.filter = if keys.pressed(KeyCode::ShiftLeft) || keys.pressed(KeyCode::ShiftRight)
+        {
+            world_grid
+                .get_building_entity(&grid_position)
+                .and_then(|entity| building_q.get(entity).ok())
+                .map(|building| building.building_type.clone())
+        } else {
+            None
+        };
+    }
+    if !input.just_released(MouseButton::Right) {
         return;
     }
-    let position = game_cursor.world_position.unwrap();
+    let Some(start) = selection.start.take() else {
+        return;
+    };
+    let filter = selection.filter.take();
 
-    let grid_position = world_grid.grid_position_from_world_position(position);
-    if let Some(cell) = world_grid.cells.get_mut(&grid_position) {
-        match cell.surface_layer {
-            SurfaceLayer::Building { entity } => {
+    let (min, max) = GridPosition::region(start, grid_position);
+    for x in min.x..=max.x {
+        for y in min.y..=max.y {
+            let grid_position = GridPosition { x, y };
+            let Some(building_entity) = world_grid.get_building_entity(&grid_position) else {
+                continue;
+            };
+            if let Some(filter) = &filter {
+                let matches = building_q
+                    .get(building_entity)
+                    .is_ok_and(|building| building.building_type == *filter);
+                if !matches {
+                    continue;
+                }
+            }
+            building_removed_event.send(BuildingRemovedEvent {
+                building_entity,
+                grid_position,
+                from_history: false,
+            });
+        }
+    }
+}
+
+pub fn draw_deconstruction_selection_system(
+    mut painter: ShapePainter,
+    game_cursor: Res<GameCursor>,
+    world_grid: Res<WorldGrid>,
+    selection: Res<DeconstructionSelection>,
+) {
+    let (Some(start), Some(position)) = (selection.start, game_cursor.world_position) else {
+        return;
+    };
+    let end = world_grid.grid_position_from_world_position(position);
+    if start == end {
+        return;
+    }
+    let (min, max) = GridPosition::region(start, end);
+    let min_position = world_grid.grid_to_world(&min);
+    let max_position = world_grid.grid_to_world(&max);
+    let size = (max_position - min_position).xz().abs() + Vec2::splat(world_grid.grid_size);
+
+    painter.hollow = false;
+    painter.color = Color::srgba(0.9, 0.2, 0.2, 0.25);
+    painter.transform =
+        Transform::from_translation((min_position + max_position) * 0.5 + Vec3::Y * 0.12)
+            .with_rotation(Quat::from_rotation_x(TAU * 0.25));
+    painter.rect(size);
+}
+
+pub fn record_building_history_system(
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    mut history: ResMut<BuildingHistory>,
+    mut building_placed_event: EventReader<BuildingPlacedEvent>,
+    mut building_removed_event: EventReader<BuildingRemovedEvent>,
+    mut building_rotated_event: EventReader<BuildingRotatedEvent>,
+    mut building_moved_event: EventReader<BuildingMovedEvent>,
+    building_q: Query<(&Building, &Transform, Has<Active>)>,
+) {
+    //undoing, redoing or loading must not become a step of its own
+    for event in building_placed_event.read().filter(|e| !e.from_history) {
+        history.record(BuildingOperation::Placed {
+            building_type: event.building_type.clone(),
+            grid_position: event.grid_position,
+            grid_rotation: event.grid_rotation,
+            active: building_q
+                .get(event.entity)
+                .map_or(true, |(_, _, active)| active),
+        });
+    }
+    for event in building_removed_event.read().filter(|e| !e.from_history) {
+        let Ok((building, transform, active)) = building_q.get(event.building_entity) else {
+            continue;
+        };
+        history.record(BuildingOperation::Removed {
+            building_type: building.building_type.clone(),
+            grid_position: event.grid_position,
+            grid_rotation: transform.grid_rotation(),
+            active,
+        });
+    }
+    for event in building_rotated_event.read().filter(|e| !e.from_history) {
+        let Ok((building, _, _)) = building_q.get(event.building_entity) else {
+            continue;
+        };
+        history.record(BuildingOperation::Rotated {
+            building_type: building.building_type.clone(),
+            grid_position: event.grid_position,
+            from: event.from,
+            to: event.to,
+        });
+    }
+    for event in building_moved_event.read().filter(|e| !e.from_history) {
+        let Ok((building, _, _)) = building_q.get(event.building_entity) else {
+            continue;
+        };
+        history.record(BuildingOperation::Moved {
+            building_type: building.building_type.clone(),
+            from: event.from,
+            to: event.to,
+        });
+    }
+    //everything done while a mouse button is held, like dragging, is undone as one step
+    if !mouse_input.pressed(MouseButton::Left) && !mouse_input.pressed(MouseButton::Right) {
+        history.finish_step();
+    }
+}
+
+pub fn undo_redo_system(
+    mut commands: Commands,
+    mut shapes: ShapeCommands,
+    mut asset_server: ResMut<AssetServer>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut history: ResMut<BuildingHistory>,
+    mut world_grid: ResMut<WorldGrid>,
+    mut inventory: ResMut<Inventory>,
+    definitions: Res<BuildingDefinitions>,
+    mut building_placed_event: EventWriter<BuildingPlacedEvent>,
+    mut building_removed_event: EventWriter<BuildingRemovedEvent>,
+    mut building_rotated_event: EventWriter<BuildingRotatedEvent>,
+    mut building_moved_event: EventWriter<BuildingMovedEvent>,
+    mut item_consumed_event: EventWriter<ItemConsumedEvent>,
+) {
+    if !keys.pressed(KeyCode::ControlLeft) && !keys.pressed(KeyCode::ControlRight) {
+        return;
+    }
+    let operations = if keys.just_pressed(KeyCode::KeyZ) {
+        history.undo()
+    } else if keys.just_pressed(KeyCode::KeyY) {
+        history.redo()
+    } else {
+        return;
+    };
+    let Some(operations) = operations else {
+        info!("nothing left in the building history");
+        return;
+    };
+    for operation in operations {
+        match operation {
+            BuildingOperation::Placed {
+                building_type,
+                grid_position,
+                grid_rotation,
+                active,
+            } => {
+                if world_grid.get_building_entity(&grid_position).is_some() {
+                    warn!(
+                        "can't restore {:?}, {:?} is occupied",
+                        building_type, grid_position
+                    );
+                    continue;
+                }
+                let cost = definitions.cost(&building_type);
+                if !inventory.can_afford(&cost) {
+                    warn!("not enough items to restore {:?}", building_type);
+                    continue;
+                }
+                if spawn_building_on_grid(
+                    building_type,
+                    grid_position,
+                    grid_rotation,
+                    active,
+                    true,
+                    &definitions,
+                    &mut commands,
+                    &mut asset_server,
+                    &mut shapes,
+                    &mut world_grid,
+                    &mut building_placed_event,
+                )
+                .is_some()
+                {
+                    inventory.spend(&cost);
+                    item_consumed_event.send_batch(ItemConsumedEvent::for_cost(&cost));
+                }
+            }
+            BuildingOperation::Removed { grid_position, .. } => {
+                let Some(building_entity) = world_grid.get_building_entity(&grid_position) else {
+                    continue;
+                };
                 building_removed_event.send(BuildingRemovedEvent {
-                    building_entity: entity,
+                    building_entity,
+                    grid_position,
+                    from_history: true,
+                });
+            }
+            BuildingOperation::Rotated {
+                grid_position,
+                from,
+                to,
+                ..
+            } => {
+                let Some(building_entity) = world_grid.get_building_entity(&grid_position) else {
+                    continue;
+                };
+                building_rotated_event.send(BuildingRotatedEvent {
+                    building_entity,
                     grid_position,
+                    from,
+                    to,
+                    from_history: true,
+                });
+            }
+            BuildingOperation::Moved { from, to, .. } => {
+                let Some(building_entity) = world_grid.get_building_entity(&from) else {
+                    continue;
+                };
+                building_moved_event.send(BuildingMovedEvent {
+                    building_entity,
+                    from,
+                    to,
+                    from_history: true,
                 });
             }
-            _ => return,
         }
     }
 }
@@ -108,11 +578,25 @@ pub fn destroy_building_system(
     mut command: Commands,
     mut building_removed_event: EventReader<BuildingRemovedEvent>,
     mut world_grid: ResMut<WorldGrid>,
+    mut inventory: ResMut<Inventory>,
+    definitions: Res<BuildingDefinitions>,
+    building_q: Query<&Building>,
 ) {
     for event in building_removed_event.read() {
+        //deconstruction gives back what the building cost
+        if let Ok(building) = building_q.get(event.building_entity) {
+            inventory.refund(&definitions.cost(&building.building_type));
+        }
         command.entity(event.building_entity).despawn_recursive();
         if let Some(cell) = world_grid.cells.get_mut(&event.grid_position) {
-            cell.surface_layer = SurfaceLayer::Empty;
+            //a replacement may already occupy the cell
+            if cell.surface_layer
+                == (SurfaceLayer::Building {
+                    entity: event.building_entity,
+                })
+            {
+                cell.surface_layer = SurfaceLayer::Empty;
+            }
         }
     }
 }
@@ -122,22 +606,28 @@ pub fn respond_to_conveyor_belt_placement_event(
     mut building_placed_event: EventReader<BuildingPlacedEvent>,
     mut belt_q: Query<&mut BeltElement>,
     mut conveyor_placed_event: EventWriter<ConveyorPlacedEvent>,
+    definitions: Res<BuildingDefinitions>,
 ) {
     for building_placed in building_placed_event.read() {
-        if !matches!(building_placed.building_type, BuildingType::ConveyorBelt) {
+        let Some(tier) = definitions.belt_tier(&building_placed.building_type) else {
+            continue;
+        };
+        let mut belt_element = belt_q.get_mut(building_placed.entity).expect(&format!(
+            "the placed building entity exists as a belt element {:?}",
+            building_placed
+        ));
+        //belts placed as a line already share a conveyor
+        if belt_element.conveyor_belt.is_some() {
             continue;
         }
         let belt_piece = BeltPiece {
             grid_rotation: building_placed.grid_rotation,
             entity: building_placed.entity,
             grid_position: building_placed.grid_position,
+            tier,
         };
 
         let conveyor_belt = ConveyorBelt::spawn_new(&mut commands, belt_piece);
-        let mut belt_element = belt_q.get_mut(building_placed.entity).expect(&format!(
-            "the placed building entity exists as a belt element {:?}",
-            building_placed
-        ));
         belt_element.conveyor_belt = Some(conveyor_belt);
         conveyor_placed_event.send(ConveyorPlacedEvent {
             entity: conveyor_belt,
@@ -151,70 +641,498 @@ pub fn respond_to_belt_element_removal(
     mut belt_q: Query<&mut BeltElement>,
     mut conveyor_q: Query<&mut ConveyorBelt>,
     world_grid: Res<WorldGrid>,
+    conveyor_graph: Res<ConveyorGraph>,
+    mut inventory: ResMut<Inventory>,
 ) {
+    //pieces removed in the same frame are handled per conveyor, so each conveyor splits only once
+    let mut removed_pieces: HashMap<Entity, Vec<Entity>> = HashMap::new();
     for event in building_removed_event.read() {
         let Ok(belt) = belt_q.get(event.building_entity) else {
-            return;
+            continue;
         };
         let Some(conveyor_entity) = belt.conveyor_belt else {
-            return;
+            continue;
         };
+        removed_pieces
+            .entry(conveyor_entity)
+            .or_default()
+            .push(event.building_entity);
+    }
+
+    for (conveyor_entity, removed) in removed_pieces {
         let Ok(mut conveyor) = conveyor_q.get_mut(conveyor_entity) else {
-            return;
+            continue;
         };
-        conveyor.items.retain(|item| {
-            let grid_position = world_grid.grid_position_from_world_position(item.position);
-            if event.grid_position == grid_position {
-                commands.entity(item.item_entity).despawn_recursive();
-                false
-            } else {
-                true
-            }
-        });
-        if conveyor.belt_pieces.first().unwrap().entity == event.building_entity {
-            conveyor.belt_pieces.remove(0);
-            if conveyor.belt_pieces.is_empty() {
-                commands.entity(conveyor_entity).despawn_recursive();
-                return;
-            }
-            commands.trigger_targets(ConveyorSegmentsChanged, conveyor_entity);
-        } else if conveyor.belt_pieces.last().unwrap().entity == event.building_entity {
-            let new_length = conveyor.belt_pieces.len() - 1;
-            conveyor.belt_pieces.truncate(new_length);
-            if conveyor.belt_pieces.is_empty() {
-                commands.entity(conveyor_entity).despawn_recursive();
-                return;
-            }
-            commands.trigger_targets(ConveyorSegmentsChanged, conveyor_entity);
-        } else {
-            let index = conveyor
-                .belt_pieces
-                .iter()
-                .position(|&b| b.entity == event.building_entity)
-                .unwrap();
-            let before = conveyor.belt_pieces[0..index].to_vec();
-            let after = conveyor.belt_pieces[index + 1..].to_vec();
-            conveyor.belt_pieces = before;
-            commands.trigger_targets(ConveyorSegmentsChanged, conveyor_entity);
-
-            let conveyor_belt = ConveyorBelt {
-                belt_pieces: after[..].to_vec(),
-                ..default()
-            };
-            if (conveyor_belt.belt_pieces.len() == 0) {
-                error!("this is very unexpected {:?}", conveyor.belt_pieces);
-            }
-            let new_conveyor_entity = commands.spawn(conveyor_belt).id();
+        //the pieces left between removed ones form the new conveyors, items on removed pieces
+        //are picked up
+        let lines = conveyor.lines_without(&removed);
+        let (line_items, picked_up) = conveyor.take_items_for_lines(&lines, &removed, &world_grid);
+        for item in picked_up {
+            inventory.add(item.item_type, 1);
+        }
+        conveyor.is_loop = false;
+
+        let mut lines = lines.into_iter().zip(line_items);
+        let Some((pieces, items)) = lines.next() else {
+            commands.entity(conveyor_entity).despawn_recursive();
+            continue;
+        };
+        conveyor.belt_pieces = pieces;
+        conveyor.pending_items = items;
+        commands.trigger_targets(ConveyorSegmentsChanged, conveyor_entity);
 
-            for belt in after.iter() {
+        for (pieces, items) in lines {
+            let new_conveyor_entity =
+                ConveyorBelt::spawn_with_pieces(&mut commands, pieces.clone(), items);
+            for belt in pieces.iter() {
                 let mut belt_element = belt_q.get_mut(belt.entity).unwrap();
                 belt_element.conveyor_belt = Some(new_conveyor_entity);
             }
             commands.trigger_targets(ConveyorSegmentsChanged, new_conveyor_entity);
         }
+        //feeding belts connect to the start, which stays with this conveyor unless the start
+        //piece itself was removed, rebuilding their segments drops a connection that is gone
+        for upstream in conveyor_graph.upstream(conveyor_entity) {
+            commands.trigger_targets(ConveyorSegmentsChanged, upstream);
+        }
     }
 }
 
+pub fn rotate_building_system(
+    input: Res<ButtonInput<KeyCode>>,
+    game_cursor: Res<GameCursor>,
+    world_grid: Res<WorldGrid>,
+    blueprint_tool: Res<BlueprintTool>,
+    move_tool: Res<MoveTool>,
+    transform_q: Query<&Transform, With<Building>>,
+    mut building_rotated_event: EventWriter<BuildingRotatedEvent>,
+) {
+    //R turns the preview or the blueprint while one of them is active
+    if !input.just_pressed(KeyCode::KeyR)
+        || game_cursor.preview_entity.is_some()
+        || blueprint_tool.mode != BlueprintMode::Idle
+    {
+        return;
+    }
+    let carried = move_tool.carried;
+    let hovered = game_cursor.world_position.and_then(|position| {
+        let grid_position = world_grid.grid_position_from_world_position(position);
+        world_grid
+            .get_building_entity(&grid_position)
+            .map(|entity| (entity, grid_position))
+    });
+    let Some((building_entity, grid_position)) = carried.or(hovered) else {
+        return;
+    };
+    let Ok(transform) = transform_q.get(building_entity) else {
+        return;
+    };
+    let from = transform.grid_rotation();
+    building_rotated_event.send(BuildingRotatedEvent {
+        building_entity,
+        grid_position,
+        from,
+        to: from.rotated_quarter(),
+        from_history: false,
+    });
+}
+
+pub fn respond_to_building_rotation(
+    mut commands: Commands,
+    mut building_rotated_event: EventReader<BuildingRotatedEvent>,
+    mut transform_q: Query<&mut Transform, With<Building>>,
+    mut belt_q: Query<&mut BeltElement>,
+    mut conveyor_q: Query<&mut ConveyorBelt>,
+    world_grid: Res<WorldGrid>,
+    conveyor_graph: Res<ConveyorGraph>,
+    mut conveyor_placed_event: EventWriter<ConveyorPlacedEvent>,
+) {
+    for event in building_rotated_event.read() {
+        let Ok(mut transform) = transform_q.get_mut(event.building_entity) else {
+            continue;
+        };
+        transform.rotation = event.to.to_quat();
+
+        //a turned belt piece has to find its place among the conveyors again
+        let Some((mut piece, items)) = detach_belt_piece(
+            &mut commands,
+            event.building_entity,
+            &mut belt_q,
+            &mut conveyor_q,
+            &world_grid,
+            &conveyor_graph,
+        ) else {
+            continue;
+        };
+        piece.grid_rotation = event.to;
+        attach_belt_piece(
+            &mut commands,
+            piece,
+            items,
+            &mut belt_q,
+            &mut conveyor_placed_event,
+        );
+    }
+}
+
+pub fn move_building_system(
+    input: Res<ButtonInput<KeyCode>>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    game_cursor: Res<GameCursor>,
+    world_grid: Res<WorldGrid>,
+    mut move_tool: ResMut<MoveTool>,
+    definitions: Res<BuildingDefinitions>,
+    building_q: Query<&Building>,
+    mut building_moved_event: EventWriter<BuildingMovedEvent>,
+) {
+    let Some(position) = game_cursor.world_position else {
+        return;
+    };
+    let grid_position = world_grid.grid_position_from_world_position(position);
+
+    let Some((building_entity, from)) = move_tool.carried else {
+        if !input.just_pressed(KeyCode::KeyM) || game_cursor.preview_entity.is_some() {
+            return;
+        }
+        if let Some(building_entity) = world_grid.get_building_entity(&grid_position) {
+            move_tool.carried = Some((building_entity, grid_position));
+        }
+        return;
+    };
+    let Ok(building) = building_q.get(building_entity) else {
+        move_tool.carried = None;
+        return;
+    };
+    if input.just_pressed(KeyCode::KeyM)
+        || input.just_pressed(KeyCode::Escape)
+        || game_cursor.preview_entity.is_some()
+    {
+        move_tool.carried = None;
+        return;
+    }
+    if !mouse_input.just_pressed(MouseButton::Left) {
+        return;
+    }
+    if grid_position == from {
+        move_tool.carried = None;
+        return;
+    }
+    if let Err(error) = validate_placement(
+        &world_grid,
+        &definitions,
+        &building.building_type,
+        grid_position,
+    ) {
+        info!("can't move the building there: {}", error.message());
+        return;
+    }
+    building_moved_event.send(BuildingMovedEvent {
+        building_entity,
+        from,
+        to: grid_position,
+        from_history: false,
+    });
+    move_tool.carried = None;
+}
+
+pub fn respond_to_building_move(
+    mut commands: Commands,
+    mut building_moved_event: EventReader<BuildingMovedEvent>,
+    mut transform_q: Query<&mut Transform, With<Building>>,
+    mut belt_q: Query<&mut BeltElement>,
+    mut conveyor_q: Query<&mut ConveyorBelt>,
+    mut world_grid: ResMut<WorldGrid>,
+    conveyor_graph: Res<ConveyorGraph>,
+    mut conveyor_placed_event: EventWriter<ConveyorPlacedEvent>,
+) {
+    for event in building_moved_event.read() {
+        if world_grid.get_building_entity(&event.from) != Some(event.building_entity) {
+            warn!(
+                "{:?} is not at {:?} anymore",
+                event.building_entity, event.from
+            );
+            continue;
+        }
+        let target_free = world_grid
+            .get_cell(&event.to)
+            .is_some_and(|cell| cell.surface_layer == SurfaceLayer::Empty);
+        if !target_free {
+            warn!("can't move {:?} to {:?}", event.building_entity, event.to);
+            continue;
+        }
+        let Ok(mut transform) = transform_q.get_mut(event.building_entity) else {
+            continue;
+        };
+        //the entity itself moves so the building keeps its state
+        let offset = world_grid.grid_to_world(&event.to) - world_grid.grid_to_world(&event.from);
+        transform.translation += offset;
+
+        let detached = detach_belt_piece(
+            &mut commands,
+            event.building_entity,
+            &mut belt_q,
+            &mut conveyor_q,
+            &world_grid,
+            &conveyor_graph,
+        );
+        world_grid.cells.get_mut(&event.from).unwrap().surface_layer = SurfaceLayer::Empty;
+        world_grid.cells.get_mut(&event.to).unwrap().surface_layer = SurfaceLayer::Building {
+            entity: event.building_entity,
+        };
+
+        let Some((mut piece, items)) = detached else {
+            continue;
+        };
+        piece.grid_position = event.to;
+        let items = items
+            .into_iter()
+            .map(|(position, item)| (position + offset, item))
+            .collect();
+        attach_belt_piece(
+            &mut commands,
+            piece,
+            items,
+            &mut belt_q,
+            &mut conveyor_placed_event,
+        );
+    }
+}
+
+pub fn draw_move_tool_system(
+    mut painter: ShapePainter,
+    game_cursor: Res<GameCursor>,
+    world_grid: Res<WorldGrid>,
+    move_tool: Res<MoveTool>,
+    definitions: Res<BuildingDefinitions>,
+    building_q: Query<&Building>,
+) {
+    let Some((building_entity, from)) = move_tool.carried else {
+        return;
+    };
+    let (Some(position), Ok(building)) =
+        (game_cursor.world_position, building_q.get(building_entity))
+    else {
+        return;
+    };
+    let grid_position = world_grid.grid_position_from_world_position(position);
+    let ghost_size = world_grid.grid_size * 0.9;
+
+    painter.hollow = true;
+    painter.thickness = 0.03;
+    painter.color = Color::srgba(1.0, 1.0, 1.0, 0.6);
+    painter.transform =
+        Transform::from_translation(world_grid.grid_to_world(&from) + Vec3::Y * 0.12)
+            .with_rotation(Quat::from_rotation_x(TAU * 0.25));
+    painter.rect(Vec2::splat(ghost_size));
+
+    painter.hollow = false;
+    painter.color = if can_place_building(
+        &world_grid,
+        &definitions,
+        &building.building_type,
+        grid_position,
+    ) {
+        Color::srgba(0.2, 0.8, 0.3, 0.35)
+    } else {
+        Color::srgba(0.9, 0.2, 0.2, 0.35)
+    };
+    painter.transform =
+        Transform::from_translation(world_grid.grid_to_world(&grid_position) + Vec3::Y * 0.12)
+            .with_rotation(Quat::from_rotation_x(TAU * 0.25));
+    painter.rect(Vec2::splat(ghost_size));
+}
+
+pub fn upgrade_belt_system(
+    input: Res<ButtonInput<KeyCode>>,
+    game_cursor: Res<GameCursor>,
+    world_grid: Res<WorldGrid>,
+    mut inventory: ResMut<Inventory>,
+    definitions: Res<BuildingDefinitions>,
+    research: Res<Research>,
+    tech_trees: Res<Assets<TechTree>>,
+    tech_tree_handle: Res<TechTreeHandle>,
+    belt_q: Query<(&BeltElement, &Building)>,
+    mut belt_upgraded_event: EventWriter<BeltUpgradedEvent>,
+    mut item_consumed_event: EventWriter<ItemConsumedEvent>,
+) {
+    if !input.just_pressed(KeyCode::KeyU) {
+        return;
+    }
+    let Some(position) = game_cursor.world_position else {
+        return;
+    };
+    let grid_position = world_grid.grid_position_from_world_position(position);
+    let Some(building_entity) = world_grid.get_building_entity(&grid_position) else {
+        return;
+    };
+    let Ok((belt, building)) = belt_q.get(building_entity) else {
+        return;
+    };
+    let Some(tier) = belt.tier.upgraded() else {
+        return;
+    };
+    let Some(building_type) = definitions.belt(tier) else {
+        return;
+    };
+    if !research.is_building_unlocked(tech_trees.get(&tech_tree_handle.0), &building_type) {
+        info!("{} is not researched yet", building_type.id());
+        return;
+    }
+    //the old belt is given back, so only the difference is paid
+    let old_cost = definitions.cost(&building.building_type);
+    inventory.refund(&old_cost);
+    let new_cost = definitions.cost(&building_type);
+    if !inventory.spend(&new_cost) {
+        inventory.spend(&old_cost);
+        info!("not enough items to upgrade the belt");
+        return;
+    }
+    for (item_type, amount) in new_cost {
+        let refunded = old_cost
+            .iter()
+            .find(|(old_item_type, _)| *old_item_type == item_type)
+            .map_or(0, |(_, old_amount)| *old_amount);
+        if amount > refunded {
+            item_consumed_event.send(ItemConsumedEvent {
+                item_type,
+                amount: amount - refunded,
+            });
+        }
+    }
+    belt_upgraded_event.send(BeltUpgradedEvent {
+        building_entity,
+        building_type,
+        tier,
+    });
+}
+
+pub fn respond_to_belt_upgrade(
+    mut commands: Commands,
+    mut belt_upgraded_event: EventReader<BeltUpgradedEvent>,
+    mut belt_q: Query<&mut BeltElement>,
+    mut building_q: Query<&mut Building>,
+    mut arrow_q: Query<(&Parent, &mut ShapeFill), With<BeltArrow>>,
+    mut conveyor_q: Query<&mut ConveyorBelt>,
+) {
+    for event in belt_upgraded_event.read() {
+        let Ok(mut belt) = belt_q.get_mut(event.building_entity) else {
+            continue;
+        };
+        if belt.tier == event.tier {
+            continue;
+        }
+        belt.tier = event.tier;
+        let conveyor_entity = belt.conveyor_belt;
+        if let Ok(mut building) = building_q.get_mut(event.building_entity) {
+            building.building_type = event.building_type.clone();
+        }
+        for (parent, mut fill) in arrow_q.iter_mut() {
+            if parent.get() == event.building_entity {
+                fill.color = event.tier.color();
+            }
+        }
+
+        //the piece stays on its conveyor, only its part of the belt moves at the new speed
+        let Some(conveyor_entity) = conveyor_entity else {
+            continue;
+        };
+        let Ok(mut conveyor) = conveyor_q.get_mut(conveyor_entity) else {
+            continue;
+        };
+        if let Some(piece) = conveyor
+            .belt_pieces
+            .iter_mut()
+            .find(|piece| piece.entity == event.building_entity)
+        {
+            piece.tier = event.tier;
+        }
+        commands.trigger_targets(ConveyorSegmentsChanged, conveyor_entity);
+    }
+}
+
+///Takes the belt piece out of its conveyor while the rest of the conveyor keeps its items,
+///returns the piece together with the items on it
+fn detach_belt_piece(
+    commands: &mut Commands,
+    building_entity: Entity,
+    belt_q: &mut Query<&mut BeltElement>,
+    conveyor_q: &mut Query<&mut ConveyorBelt>,
+    world_grid: &WorldGrid,
+    conveyor_graph: &ConveyorGraph,
+) -> Option<(BeltPiece, Vec<(Vec3, BeltItem)>)> {
+    let conveyor_entity = belt_q.get(building_entity).ok()?.conveyor_belt?;
+    let mut conveyor = conveyor_q.get_mut(conveyor_entity).ok()?;
+
+    let mut index = conveyor
+        .belt_pieces
+        .iter()
+        .position(|b| b.entity == building_entity)?;
+    if conveyor.is_loop {
+        conveyor.belt_pieces.rotate_left(index);
+        conveyor.is_loop = false;
+        index = 0;
+    }
+    let piece = conveyor.belt_pieces[index];
+    let before = conveyor.belt_pieces[..index].to_vec();
+    let after = conveyor.belt_pieces[index + 1..].to_vec();
+
+    let mut before_items = vec![];
+    let mut piece_items = vec![];
+    let mut after_items = vec![];
+    for (position, item) in conveyor.take_items() {
+        let grid_position = world_grid.grid_position_from_world_position(position);
+        if grid_position == piece.grid_position {
+            piece_items.push((position, item));
+        } else if before.iter().any(|b| b.grid_position == grid_position) {
+            before_items.push((position, item));
+        } else if !after.is_empty() {
+            after_items.push((position, item));
+        } else {
+            piece_items.push((position, item));
+        }
+    }
+
+    conveyor.pending_items = before_items;
+    conveyor.belt_pieces = before;
+    if conveyor.belt_pieces.is_empty() {
+        commands.entity(conveyor_entity).despawn_recursive();
+    } else {
+        commands.trigger_targets(ConveyorSegmentsChanged, conveyor_entity);
+    }
+
+    if !after.is_empty() {
+        let after_conveyor_entity =
+            ConveyorBelt::spawn_with_pieces(commands, after.clone(), after_items);
+        for belt in after.iter() {
+            let mut belt_element = belt_q.get_mut(belt.entity).unwrap();
+            belt_element.conveyor_belt = Some(after_conveyor_entity);
+        }
+        commands.trigger_targets(ConveyorSegmentsChanged, after_conveyor_entity);
+    }
+
+    for upstream in conveyor_graph.upstream(conveyor_entity) {
+        commands.trigger_targets(ConveyorSegmentsChanged, upstream);
+    }
+    belt_q.get_mut(building_entity).unwrap().conveyor_belt = None;
+    Some((piece, piece_items))
+}
+
+///Gives the belt piece a conveyor of its own that gets merged with its neighbours once placed
+fn attach_belt_piece(
+    commands: &mut Commands,
+    piece: BeltPiece,
+    items: Vec<(Vec3, BeltItem)>,
+    belt_q: &mut Query<&mut BeltElement>,
+    conveyor_placed_event: &mut EventWriter<ConveyorPlacedEvent>,
+) {
+    let conveyor_entity = ConveyorBelt::spawn_with_pieces(commands, vec![piece], items);
+    let mut belt_element = belt_q.get_mut(piece.entity).unwrap();
+    belt_element.conveyor_belt = Some(conveyor_entity);
+    conveyor_placed_event.send(ConveyorPlacedEvent {
+        entity: conveyor_entity,
+    });
+}
+
 pub fn handle_conveyor_placement_system(
     mut commands: Commands,
     mut belt_element_placed_event: EventReader<ConveyorPlacedEvent>,
@@ -267,6 +1185,31 @@ pub fn handle_conveyor_placement_system(
         .map(|e| *e)
         .collect::<Vec<_>>();
 
+        //a line placed in one go can also connect at its end piece
+        if conveyor.belt_pieces.len() > 1 {
+            let end_piece = conveyor.belt_pieces.last().unwrap();
+            let end_neighbours = [
+                end_piece.relative_forward_position(),
+                end_piece
+                    .grid_position
+                    .get_relative_left(end_piece.grid_rotation),
+                end_piece
+                    .grid_position
+                    .get_relative_right(end_piece.grid_rotation),
+            ];
+            for position in end_neighbours {
+                let Some(entity) = retrieve_conveyor_from_grid(position, &world_grid, &belt_q)
+                else {
+                    continue;
+                };
+                if entity != primary_conveyor_entity
+                    && !conveyors_entities_to_check.contains(&entity)
+                {
+                    conveyors_entities_to_check.push(entity);
+                }
+            }
+        }
+
         for _ in 0..conveyors_entities_to_check.len() {
             let Some(&next_conveyor_entity) = conveyors_entities_to_check.first() else {
                 break;
@@ -289,6 +1232,15 @@ pub fn handle_conveyor_placement_system(
                 }
             }
         }
+        if let Ok(mut conveyor) = conveyor_q.get_mut(primary_conveyor_entity) {
+            if !conveyor.is_loop && conveyor.closes_loop() {
+                info!(
+                    "conveyor {:?} got closed into a loop",
+                    primary_conveyor_entity
+                );
+                conveyor.is_loop = true;
+            }
+        }
         commands.trigger_targets(ConveyorSegmentsChanged, primary_conveyor_entity);
     }
 }
@@ -340,6 +1292,9 @@ fn check_for_conveyor_merge(
     else {
         return None;
     };
+    if primary_conveyor.is_loop || secondary_conveyor.is_loop {
+        return None;
+    }
     let Some(secondary_end_piece) = secondary_conveyor.belt_pieces.last() else {
         return None;
     };
@@ -355,6 +1310,8 @@ fn check_for_conveyor_merge(
         secondary_conveyor
             .belt_pieces
             .append(&mut primary_conveyor.belt_pieces);
+        let mut items = primary_conveyor.take_items();
+        secondary_conveyor.pending_items.append(&mut items);
 
         commands.entity(primary_conveyor_entity).despawn_recursive();
         return Some(secondary_conveyor_entity);
@@ -367,6 +1324,8 @@ fn check_for_conveyor_merge(
         primary_conveyor
             .belt_pieces
             .append(&mut secondary_conveyor.belt_pieces);
+        let mut items = secondary_conveyor.take_items();
+        primary_conveyor.pending_items.append(&mut items);
 
         commands
             .entity(secondary_conveyor_entity)
@@ -379,19 +1338,31 @@ fn check_for_conveyor_merge(
 }
 
 pub fn extract_resources_system(
+    mut commands: Commands,
     time: Res<Time>,
-    world_grid: Res<WorldGrid>,
-    mut extractor_q: Query<(&mut Extractor, &Transform), With<Active>>,
+    mut world_grid: ResMut<WorldGrid>,
+    mut extractor_q: Query<(&mut Extractor, &mut BuildingStatus, &Transform), With<Active>>,
+    mut node_q: Query<&mut YellowBileResource>,
     mut belt_q: Query<&mut BeltElement>,
     mut conveyor_q: Query<&mut ConveyorBelt>,
-    mut shapes: ShapeCommands,
+    mut item_produced_event: EventWriter<ItemProducedEvent>,
 ) {
-    for (mut extractor, transform) in extractor_q.iter_mut() {
+    for (mut extractor, mut status, transform) in extractor_q.iter_mut() {
         extractor.timer.tick(time.delta());
         if !extractor.timer.finished() {
             continue;
         }
         let grid_position = world_grid.grid_position_from_world_position(transform.translation);
+        if world_grid
+            .cells
+            .get(&grid_position)
+            .is_none_or(|cell| cell.resource_node.is_none())
+        {
+            status.set_if_neq(BuildingStatus::NoResource);
+            continue;
+        }
+        //without a belt with free space next to it the extractor can't hand anything out
+        let mut new_status = BuildingStatus::OutputBlocked;
         let potential_positions = grid_position.get_all_surrounding_positions();
         for p in potential_positions.iter() {
             let Some(cell) = world_grid.cells.get(p) else {
@@ -414,39 +1385,177 @@ pub fn extract_resources_system(
             };
             let item_width = 0.2;
             let position = world_grid.grid_to_world(&p);
-            if !conveyor.has_space_at_position(position, item_width, None) {
+            if !conveyor.has_space_at_position(position, item_width) {
                 continue;
             }
-
-            let item_entity =
-                YellowBileItem::spawn(world_grid.grid_to_world(&p), Quat::IDENTITY, &mut shapes);
-
-            let index = conveyor
-                .get_segment_index_for_position(position, false)
-                .expect(&format!(
-                    "Somehow item not on any segment {} - {:?}",
-                    position, conveyor.segments
-                ));
-            let progress = conveyor.segments[index].progress_for_point(position);
-            conveyor.items.push(BeltItem {
-                position: world_grid.grid_to_world(&p),
-                item_entity,
-                segment_index: index,
-                segment_progress: progress,
-                item_width,
+            //every item comes out of the node below, a depleted node stops the extractor
+            let Some(item_type) =
+                mine_resource_node(&mut commands, &mut world_grid, &mut node_q, &grid_position)
+            else {
+                new_status = BuildingStatus::NoResource;
+                break;
+            };
+            new_status = BuildingStatus::Working;
+            item_produced_event.send(ItemProducedEvent {
+                item_type,
+                amount: 1,
             });
+
+            conveyor.insert_item(
+                position,
+                BeltItem {
+                    item_type,
+                    item_width,
+                },
+            );
+        }
+        status.set_if_neq(new_status);
+    }
+}
+
+///Switches the building under the cursor on or off
+pub fn toggle_active_system(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    game_cursor: Res<GameCursor>,
+    world_grid: Res<WorldGrid>,
+    building_q: Query<(&Building, Has<Active>), Without<Preview>>,
+    belt_q: Query<&BeltElement>,
+    mut conveyor_q: Query<&mut ConveyorBelt>,
+) {
+    if !keys.just_pressed(KeyCode::KeyO) {
+        return;
+    }
+    let Some(position) = game_cursor.world_position else {
+        return;
+    };
+    let grid_position = world_grid.grid_position_from_world_position(position);
+    let Some(building_entity) = world_grid.get_building_entity(&grid_position) else {
+        return;
+    };
+    let Ok((building, active)) = building_q.get(building_entity) else {
+        return;
+    };
+    if active {
+        info!("switched off {}", building.building_type.id());
+        commands.entity(building_entity).remove::<Active>();
+    } else {
+        info!("switched on {}", building.building_type.id());
+        commands.entity(building_entity).insert(Active {});
+    }
+    //the conveyor of a belt piece keeps count, so it does not have to look at every piece each tick
+    let conveyor_entity = belt_q
+        .get(building_entity)
+        .ok()
+        .and_then(|belt| belt.conveyor_belt);
+    if let Some(mut conveyor) = conveyor_entity.and_then(|entity| conveyor_q.get_mut(entity).ok()) {
+        if active {
+            conveyor.inactive_pieces += 1;
+        } else {
+            conveyor.inactive_pieces = conveyor.inactive_pieces.saturating_sub(1);
+        }
+    }
+}
+
+///Greys out the models of switched off buildings and gives them their colours back once they run again
+pub fn disabled_tint_system(
+    mut commands: Commands,
+    disabled_material: Res<DisabledMaterial>,
+    disabled_q: Query<Entity, (With<Building>, Without<Active>, Without<Preview>)>,
+    enabled_q: Query<Entity, (With<Building>, Added<Active>)>,
+    children_q: Query<&Children>,
+    mut material_q: Query<(
+        &mut MeshMaterial3d<StandardMaterial>,
+        Option<&OriginalMaterial>,
+    )>,
+) {
+    //the scene of a building spawns later, so the tint is applied every frame
+    for building_entity in disabled_q.iter() {
+        for entity in children_q.iter_descendants(building_entity) {
+            let Ok((mut mesh_material, original)) = material_q.get_mut(entity) else {
+                continue;
+            };
+            if mesh_material.0 == disabled_material.0 {
+                continue;
+            }
+            if original.is_none() {
+                commands
+                    .entity(entity)
+                    .insert(OriginalMaterial(mesh_material.0.clone()));
+            }
+            mesh_material.0 = disabled_material.0.clone();
+        }
+    }
+    for building_entity in enabled_q.iter() {
+        for entity in children_q.iter_descendants(building_entity) {
+            let Ok((mut mesh_material, Some(original))) = material_q.get_mut(entity) else {
+                continue;
+            };
+            mesh_material.0 = original.0.clone();
+            commands.entity(entity).remove::<OriginalMaterial>();
+        }
+    }
+}
+
+///Machines that are switched off show it instead of their last status
+pub fn disabled_status_system(
+    mut status_q: Query<&mut BuildingStatus, (Without<Active>, Without<Preview>)>,
+) {
+    for mut status in status_q.iter_mut() {
+        status.set_if_neq(BuildingStatus::Disabled);
+    }
+}
+
+///Puts a status billboard above every placed machine
+pub fn spawn_status_icon_system(
+    mut commands: Commands,
+    general_assets: Res<GeneralAssets>,
+    status_q: Query<(Entity, &BuildingStatus), (Added<BuildingStatus>, Without<Preview>)>,
+) {
+    for (entity, status) in status_q.iter() {
+        commands.entity(entity).with_children(|parent| {
+            parent.spawn((
+                BillboardText::new(status.icon()),
+                TextFont {
+                    font: general_assets.default_font.clone(),
+                    font_size: 60.0,
+                    ..default()
+                },
+                TextColor(status.color()),
+                Transform::from_translation(Vec3::Y * 1.1).with_scale(Vec3::splat(0.005)),
+                StatusIcon,
+                Name::new("Status Icon"),
+            ));
+        });
+    }
+}
+
+pub fn update_status_icon_system(
+    status_q: Query<(&BuildingStatus, &Children), Changed<BuildingStatus>>,
+    mut icon_q: Query<(&mut BillboardText, &mut TextColor), With<StatusIcon>>,
+) {
+    for (status, children) in status_q.iter() {
+        let mut icons = icon_q.iter_many_mut(children);
+        while let Some((mut text, mut color)) = icons.fetch_next() {
+            *text = BillboardText::new(status.icon());
+            color.0 = status.color();
         }
     }
 }
 
 pub fn inserter_animation_system(
-    mut inserter_q: Query<(Entity, &mut Inserter), Without<Preview>>,
+    mut inserter_q: Query<
+        (Entity, &mut Inserter, &mut BuildingStatus),
+        (With<Active>, Without<Preview>),
+    >,
     mut transform_q: Query<&mut Transform>,
     children_q: Query<&Children>,
     name_q: Query<&Name>,
     time: Res<Time>,
 ) {
-    for (entity, mut inserter) in inserter_q.iter_mut() {
+    for (entity, mut inserter, mut status) in inserter_q.iter_mut() {
+        //inserters don't move items yet, a running one is idle and a switched off one disabled
+        status.set_if_neq(BuildingStatus::Idle);
         if inserter.rotation_spot.is_none() {
             inserter.rotation_spot =
                 find_child_with_name(entity, "element-d", &children_q, &name_q);
@@ -475,3 +1584,19 @@ pub fn inserter_animation_system(
         }
     }
 }
+
+pub fn reset_building_system(
+    mut history: ResMut<BuildingHistory>,
+    mut conveyor_graph: ResMut<ConveyorGraph>,
+    mut belt_item_batches: ResMut<BeltItemBatches>,
+    mut belt_line_plan: ResMut<BeltLinePlan>,
+    mut move_tool: ResMut<MoveTool>,
+    mut deconstruction_selection: ResMut<DeconstructionSelection>,
+) {
+    history.clear();
+    *conveyor_graph = ConveyorGraph::default();
+    *belt_item_batches = BeltItemBatches::default();
+    belt_line_plan.clear();
+    *move_tool = MoveTool::default();
+    *deconstruction_selection = DeconstructionSelection::default();
+}
@@ -1,28 +1,63 @@
-use crate::building::building_components::{BeltItem, BeltPiece, ConveyorSegment};
+use crate::building::belt_lane::BeltTrack;
+use crate::building::building_components::{
+    BeltItem, BeltPiece, BeltTier, ConveyorSegment, ItemType,
+};
 use crate::world_grid::world_gird_components::*;
+use crate::AppState;
 use crate::ReflectComponent;
 use bevy::core::Name;
 use bevy::math::Vec3;
 use bevy::prelude::*;
-use bevy::utils::info;
+use bevy::utils::{info, HashMap};
 
 #[derive(Component, Default, Reflect, Debug)]
 #[reflect(Component)]
 pub struct ConveyorBelt {
     //belt pieces first is at the start, last at the end
     pub belt_pieces: Vec<BeltPiece>,
-    pub items: Vec<BeltItem>,
+    pub items: BeltTrack,
+    //split where the direction or the tier changes, so every segment has a single speed
     pub segments: Vec<ConveyorSegment>,
-    pub belt_speed: f32,
     pub connected_conveyor_belt: Option<Entity>,
+    //a loop has no start or end piece, its last piece feeds into the first one
+    pub is_loop: bool,
+    //the front item waits at the end of the belt, the hand over is retried every tick
+    pub blocked: bool,
+    //switched off pieces, the whole conveyor stops while there is one
+    pub inactive_pieces: usize,
+    //items handed over from other conveyors, placed on the lane once the segments are rebuilt
+    #[reflect(ignore)]
+    pub pending_items: Vec<(Vec3, BeltItem)>,
 }
 
 #[derive(Event)]
 pub struct ConveyorSegmentsChanged;
 
+///Positions of all belt items grouped by item type and conveyor, drawn as one batch per type.
+///Only conveyors whose items changed are rebuilt
+#[derive(Resource, Default)]
+pub struct BeltItemBatches {
+    pub batches: HashMap<ItemType, HashMap<Entity, Vec<Vec3>>>,
+}
+
+impl BeltItemBatches {
+    pub fn remove(&mut self, conveyor: Entity) {
+        for conveyors in self.batches.values_mut() {
+            conveyors.remove(&conveyor);
+        }
+    }
+}
+
+#[derive(Event, Debug)]
+pub struct ConveyorBlockedChanged {
+    pub conveyor: Entity,
+    pub blocked: bool,
+}
+
 #[derive(Event)]
 pub struct ItemReachedOtherBeltTrigger {
     pub belt_item: BeltItem,
+    pub position: Vec3,
     pub next_conveyor: Entity,
 }
 
@@ -42,20 +77,110 @@ impl ConveyorBelt {
     }
 
     pub fn spawn_new(commands: &mut Commands, belt_piece: BeltPiece) -> Entity {
+        Self::spawn_with_pieces(commands, vec![belt_piece], vec![])
+    }
+
+    pub fn spawn_with_pieces(
+        commands: &mut Commands,
+        belt_pieces: Vec<BeltPiece>,
+        pending_items: Vec<(Vec3, BeltItem)>,
+    ) -> Entity {
         let conveyor_belt_entity = commands
             .spawn((
                 ConveyorBelt {
-                    belt_pieces: vec![belt_piece],
-                    belt_speed: 2.50,
+                    belt_pieces,
+                    pending_items,
                     ..default()
                 },
                 Name::new("Conveyor"),
+                StateScoped(AppState::Game),
             ))
             .id();
         conveyor_belt_entity
     }
 
+    ///The end piece points into the start piece, so the conveyor can be closed into a loop
+    pub fn closes_loop(&self) -> bool {
+        let (Some(start_piece), Some(end_piece)) =
+            (self.belt_pieces.first(), self.belt_pieces.last())
+        else {
+            return false;
+        };
+        self.belt_pieces.len() > 2
+            && end_piece.relative_forward_position() == start_piece.grid_position
+            && end_piece
+                .grid_rotation
+                .difference(start_piece.grid_rotation)
+                <= 1
+    }
+
+    ///The pieces left once the removed ones are gone, split into the lines between them. A loop
+    ///opens up, the piece after a removed one becomes the new start
+    pub fn lines_without(&self, removed: &[Entity]) -> Vec<Vec<BeltPiece>> {
+        let mut pieces = self.belt_pieces.clone();
+        if self.is_loop {
+            if let Some(index) = pieces.iter().position(|b| removed.contains(&b.entity)) {
+                pieces.rotate_left(index + 1);
+            }
+        }
+
+        let mut lines: Vec<Vec<BeltPiece>> = vec![vec![]];
+        for piece in pieces {
+            if !removed.contains(&piece.entity) {
+                lines.last_mut().unwrap().push(piece);
+            } else if !lines.last().unwrap().is_empty() {
+                lines.push(vec![]);
+            }
+        }
+        lines.retain(|pieces| !pieces.is_empty());
+        lines
+    }
+
+    ///Takes all items and hands each one to the line with the piece closest to it. Items on a
+    ///removed piece or too far away from any line are returned to be picked up
+    pub fn take_items_for_lines(
+        &mut self,
+        lines: &[Vec<BeltPiece>],
+        removed: &[Entity],
+        world_grid: &WorldGrid,
+    ) -> (Vec<Vec<(Vec3, BeltItem)>>, Vec<BeltItem>) {
+        let mut line_items = vec![vec![]; lines.len()];
+        let mut picked_up = vec![];
+        let removed_cells = self
+            .belt_pieces
+            .iter()
+            .filter(|b| removed.contains(&b.entity))
+            .map(|b| b.grid_position)
+            .collect::<Vec<_>>();
+        for (position, item) in self.take_items() {
+            let grid_position = world_grid.grid_position_from_world_position(position);
+            if removed_cells.contains(&grid_position) {
+                picked_up.push(item);
+                continue;
+            }
+            let closest = lines
+                .iter()
+                .enumerate()
+                .flat_map(|(i, pieces)| pieces.iter().map(move |b| (i, b)))
+                .map(|(i, b)| {
+                    let piece_position = world_grid.grid_to_world(&b.grid_position);
+                    (i, piece_position.distance(position))
+                })
+                .min_by(|a, b| a.1.total_cmp(&b.1));
+            match closest {
+                Some((i, distance)) if distance <= world_grid.grid_size => {
+                    line_items[i].push((position, item))
+                }
+                _ => picked_up.push(item),
+            }
+        }
+        (line_items, picked_up)
+    }
+
     pub fn get_connecting_positions_from_start(&self) -> Vec<GridPosition> {
+        if self.is_loop {
+            return vec![];
+        }
         let Some(start_piece) = self.belt_pieces.first() else {
             return vec![];
         };
@@ -70,6 +195,9 @@ impl ConveyorBelt {
     }
 
     pub fn get_connecting_positions_from_end(&self) -> Vec<GridPosition> {
+        if self.is_loop {
+            return vec![];
+        }
         let Some(last_piece) = self.belt_pieces.last() else {
             return vec![];
         };
@@ -110,45 +238,93 @@ impl ConveyorBelt {
         end_piece.relative_forward_position() == other_piece.grid_position
     }
 
-    ///Inserts item and the correct position, will not work if there is no space on the belt
-    pub fn insert_item(&mut self, item: &BeltItem) {
-        let mut item = item.clone();
-        let index = self
-            .get_segment_index_for_position(item.position, false)
-            .expect(&format!(
-                "Somehow item not on any segment {} - {:?}",
-                item.position, self.segments
-            ));
-        let progress = self.segments[index].progress_for_point(item.position);
-        item.segment_index = index;
-        item.segment_progress = progress;
+    ///Inserts item at the correct position, returns false if there is no space on the belt
+    pub fn insert_item(&mut self, position: Vec3, item: BeltItem) -> bool {
+        let Some(distance) = self.distance_for_position(position, false) else {
+            info!("no segment found for position {}", position);
+            return false;
+        };
+        self.items.insert(distance, item)
+    }
 
-        let mut index_to_insert = self.items.len();
+    ///Total length of all segments, including the connector to the next belt
+    pub fn length(&self) -> f32 {
+        self.segments.iter().map(|segment| segment.length()).sum()
+    }
 
-        for (i, existing_item) in self.items.iter().enumerate() {
-            if existing_item.segment_index > index {
-                continue;
-            }
-            if existing_item.segment_index < index {
-                index_to_insert = i;
-                break;
-            }
-            if existing_item.segment_index == index {
-                if existing_item.segment_progress >= item.segment_progress {
-                    continue;
-                } else {
-                    index_to_insert = i;
-                    break;
-                }
+    ///Length and speed of the parts of the belt that share a tier, from the start to the end
+    pub fn speed_zones(&self) -> Vec<(f32, f32)> {
+        let mut zones: Vec<(f32, BeltTier)> = vec![];
+        for segment in &self.segments {
+            match zones.last_mut() {
+                Some((length, tier)) if *tier == segment.tier => *length += segment.length(),
+                _ => zones.push((segment.length(), segment.tier)),
             }
         }
+        zones
+            .into_iter()
+            .map(|(length, tier)| (length, tier.speed()))
+            .collect()
+    }
+
+    pub fn position_for_distance(&self, distance: f32) -> Vec3 {
+        position_on_segments(&self.segments, distance)
+    }
+
+    pub fn distance_for_position(&self, position: Vec3, include_connector: bool) -> Option<f32> {
+        let index = self.get_segment_index_for_position(position, include_connector)?;
+        let offset: f32 = self.segments[..index]
+            .iter()
+            .map(|segment| segment.length())
+            .sum();
+        Some(offset + self.segments[index].distance_for_point(position))
+    }
+
+    ///Keeps only the items for which the predicate returns true, gets the world position of the item
+    pub fn retain_items(&mut self, mut f: impl FnMut(Vec3, &BeltItem) -> bool) {
+        let segments = &self.segments;
+        self.items
+            .retain(|distance, item| f(position_on_segments(segments, distance), item));
+        self.pending_items
+            .retain(|(position, item)| f(*position, item));
+    }
 
-        self.items.insert(index_to_insert, item);
+    ///Removes all items, including pending ones, and returns them with their world position
+    pub fn take_items(&mut self) -> Vec<(Vec3, BeltItem)> {
+        let mut items = self
+            .items
+            .drain()
+            .into_iter()
+            .map(|(distance, item)| (self.position_for_distance(distance), item))
+            .collect::<Vec<_>>();
+        items.append(&mut self.pending_items);
+        items
     }
 
-    pub fn remove_item(&mut self, belt_item: &BeltItem) {
+    ///Distance of the closest point on the belt, for positions that are not on any segment
+    pub fn closest_distance_for_position(&self, position: Vec3) -> Option<f32> {
+        let mut offset = 0.0;
+        let mut closest: Option<(f32, f32)> = None;
+        for segment in &self.segments {
+            let distance = segment.distance_for_point(position);
+            let error = segment
+                .position_for_progress(distance / segment.length())
+                .distance_squared(position);
+            if closest.map_or(true, |(_, closest_error)| error < closest_error) {
+                closest = Some((offset + distance, error));
+            }
+            offset += segment.length();
+        }
+        closest.map(|(distance, _)| distance)
+    }
+
+    ///World positions of all items, front item first
+    pub fn item_positions(&self) -> impl Iterator<Item = (Vec3, &BeltItem)> + '_ {
+        //items are sorted by distance, so the cursor walks the segments only once
+        let mut cursor = SegmentCursor::new(&self.segments);
         self.items
-            .retain(|item| item.item_entity != belt_item.item_entity);
+            .iter()
+            .map(move |(distance, item)| (cursor.position(distance), item))
     }
 
     #[allow(dead_code)]
@@ -190,38 +366,145 @@ impl ConveyorBelt {
         None
     }
 
-    pub fn has_space_at_position(
-        &self,
-        position: Vec3,
-        item_size: f32,
-        ignored_entity: Option<Entity>,
-    ) -> bool {
-        let Some(i) = self.get_segment_index_for_position(position, false) else {
+    pub fn has_space_at_position(&self, position: Vec3, item_size: f32) -> bool {
+        let Some(distance) = self.distance_for_position(position, false) else {
             info!("no index found for position {}", position);
             return false;
         };
-        let segment = &self.segments[i];
-        let item_width_progress = item_size / segment.length();
-        let progress = segment.progress_for_point(position);
-        for item in self.items.iter() {
-            if let Some(ignored) = ignored_entity {
-                if ignored == item.item_entity {
-                    continue;
-                }
-            }
-            // if item.segment_index < i {
-            //     continue;
-            // };
-            // if item.segment_index > i {
-            //     return true;
-            // };
-            if i == item.segment_index
-                && (progress - item.segment_progress).abs() < item_width_progress
-            {
-                return false;
-            }
+        self.items.has_space_at(distance, item_size)
+    }
+}
+
+fn position_on_segments(segments: &[ConveyorSegment], distance: f32) -> Vec3 {
+    let mut remaining = distance;
+    for segment in segments {
+        if remaining <= segment.length() {
+            return segment.position_for_progress(remaining / segment.length());
+        }
+        remaining -= segment.length();
+    }
+    segments
+        .last()
+        .map(|segment| segment.end_position())
+        .unwrap_or_default()
+}
+
+///Walks the segments from the end of the belt, cheap for positions that are looked up in order
+struct SegmentCursor<'a> {
+    segments: &'a [ConveyorSegment],
+    index: usize,
+    //distance of the start of the current segment
+    start: f32,
+}
+
+impl<'a> SegmentCursor<'a> {
+    fn new(segments: &'a [ConveyorSegment]) -> Self {
+        let length: f32 = segments.iter().map(|segment| segment.length()).sum();
+        let index = segments.len().saturating_sub(1);
+        let start = length - segments.last().map_or(0.0, |segment| segment.length());
+        Self {
+            segments,
+            index,
+            start,
         }
+    }
+
+    fn position(&mut self, distance: f32) -> Vec3 {
+        while self.index > 0 && distance < self.start {
+            self.index -= 1;
+            self.start -= self.segments[self.index].length();
+        }
+        while self.index + 1 < self.segments.len()
+            && distance > self.start + self.segments[self.index].length()
+        {
+            self.start += self.segments[self.index].length();
+            self.index += 1;
+        }
+        let Some(segment) = self.segments.get(self.index) else {
+            return Vec3::ZERO;
+        };
+        let progress = (distance - self.start) / segment.length();
+        segment.position_for_progress(progress.min(1.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::building::building_components::BeltTier;
+
+    ///A straight line of pieces going east along the x axis
+    fn conveyor(length: u32) -> ConveyorBelt {
+        let belt_pieces = (0..length)
+            .map(|i| BeltPiece {
+                entity: Entity::from_raw(i),
+                grid_rotation: GridRotation::E,
+                grid_position: GridPosition { x: i as i32, y: 0 },
+                tier: BeltTier::Slow,
+            })
+            .collect();
+        ConveyorBelt {
+            belt_pieces,
+            ..default()
+        }
+    }
+
+    fn line_entities(lines: &[Vec<BeltPiece>]) -> Vec<Vec<u32>> {
+        lines
+            .iter()
+            .map(|pieces| pieces.iter().map(|b| b.entity.index()).collect())
+            .collect()
+    }
+
+    fn item() -> BeltItem {
+        BeltItem {
+            item_type: ItemType::YellowBile,
+            item_width: 0.2,
+        }
+    }
+
+    #[test]
+    fn removed_pieces_split_the_conveyor_once() {
+        let conveyor = conveyor(8);
+        let removed = [1, 2, 5, 7].map(Entity::from_raw);
+        assert_eq!(
+            line_entities(&conveyor.lines_without(&removed)),
+            vec![vec![0], vec![3, 4], vec![6]]
+        );
+        let all = (0..8).map(Entity::from_raw).collect::<Vec<_>>();
+        assert!(conveyor.lines_without(&all).is_empty());
+    }
+
+    #[test]
+    fn removed_pieces_open_a_loop() {
+        let mut conveyor = conveyor(6);
+        conveyor.is_loop = true;
+        let removed = [2, 4].map(Entity::from_raw);
+        assert_eq!(
+            line_entities(&conveyor.lines_without(&removed)),
+            vec![vec![3], vec![5, 0, 1]]
+        );
+    }
+
+    #[test]
+    fn items_go_to_the_closest_line_that_is_left() {
+        let mut conveyor = conveyor(6);
+        let removed = [2, 3].map(Entity::from_raw);
+        let lines = conveyor.lines_without(&removed);
+        conveyor.pending_items = [0.5, 1.2, 2.1, 3.4, 4.8, 9.0]
+            .into_iter()
+            .map(|x| (Vec3::new(x, 0.0, 0.0), item()))
+            .collect();
 
-        true
+        let world_grid = WorldGrid::new(1.0);
+        let (line_items, picked_up) = conveyor.take_items_for_lines(&lines, &removed, &world_grid);
+        let xs = line_items
+            .iter()
+            .map(|items| items.iter().map(|(p, _)| p.x).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        assert_eq!(xs, vec![vec![0.5, 1.2], vec![4.8]]);
+        //two on the removed pieces and one that is off the belt
+        assert_eq!(picked_up.len(), 3);
+        assert!(conveyor.take_items().is_empty());
     }
 }
@@ -1,63 +1,76 @@
-use crate::building::building_components::{BeltElement, ConveyorSegment};
+use crate::building::belt_lane::BeltTrack;
+use crate::building::building_components::{Active, BeltElement, ConveyorSegment};
 use crate::building::conveyor_belt::{
-    ConveyorBelt, ConveyorSegmentsChanged, ItemReachedOtherBeltTrigger,
+    BeltItemBatches, ConveyorBelt, ConveyorBlockedChanged, ConveyorSegmentsChanged,
+    ItemReachedOtherBeltTrigger,
 };
-use crate::utilities::utility_methods::{RoundBeltExt, RoundExt};
-use crate::world_grid::components::yellow_bile::YellowBileItem;
+use crate::building::conveyor_graph::ConveyorGraph;
 use crate::world_grid::world_gird_components::{GridPiece, WorldGrid};
 use bevy::math::Vec3;
 use bevy::prelude::*;
-use bevy::utils::info;
+use bevy_vector_shapes::prelude::*;
+use std::f32::consts::TAU;
 
 pub fn conveyor_system(
     time: Res<Time>,
     mut q_conveyor: Query<(Entity, &mut ConveyorBelt)>,
-    mut transform_q: Query<(&mut Transform, &mut YellowBileItem), Without<BeltElement>>,
     mut commands: Commands,
-    keys: Res<ButtonInput<KeyCode>>,
-    mut is_paused: Local<bool>,
+    mut conveyor_graph: ResMut<ConveyorGraph>,
+    mut blocked_events: EventWriter<ConveyorBlockedChanged>,
 ) {
-    if (keys.just_pressed(KeyCode::KeyP)) {
-        *is_paused = !*is_paused;
-    }
-    if *is_paused {
-        return;
-    }
-    for (entity, conveyor) in q_conveyor.iter_mut() {
-        let conveyor = conveyor.into_inner();
-        let segments = &mut conveyor.segments;
-        let segments_length = segments.len();
-        let mut next_spot = vec![1.0; segments_length];
-
-        for item in &mut conveyor.items {
-            let segment = &segments[item.segment_index];
-            let previous_progress = item.segment_progress;
-            let last_segment_index = segments_length - 1;
-            let mut reached_next_belt = false;
-
-            item.segment_progress += (conveyor.belt_speed * time.delta_secs() / segment.length());
-            item.segment_progress = item.segment_progress.clamp(0.0, 1.0);
-            let item_width_progress = (item.item_width / segment.length());
-            if next_spot[item.segment_index] >= 0.0 {
-                item.segment_progress = item.segment_progress.min(next_spot[item.segment_index]);
-                next_spot[item.segment_index] = item.segment_progress - item_width_progress;
-            } else {
-                item.segment_progress = previous_progress;
-            }
+    for (entity, mut conveyor) in q_conveyor.iter_mut() {
+        //a switched off piece stops the whole conveyor it belongs to
+        if conveyor.inactive_pieces > 0 {
+            continue;
+        }
+        let delta = time.delta_secs();
+        //only a belt whose items move needs its drawn positions rebuilt
+        if delta > 0.0 && !conveyor.items.is_empty() && !conveyor.items.is_compressed() {
+            conveyor.set_changed();
+        }
+        let conveyor = conveyor.bypass_change_detection();
+        if conveyor.items.is_empty() {
+            set_conveyor_blocked(
+                entity,
+                conveyor,
+                false,
+                &mut conveyor_graph,
+                &mut blocked_events,
+            );
+            continue;
+        }
+        conveyor.items.advance(delta);
 
-            if item.segment_progress == 1.0 {
-                if item.segment_index == last_segment_index {
-                    reached_next_belt = conveyor.connected_conveyor_belt.is_some();
-                } else {
-                    let next_segment = &segments[item.segment_index + 1];
-                    let next_segment_item_width_progress =
-                        (item.item_width / next_segment.length());
-                    onveyor: conveyor.connected_conveyor_belt.unwrap(),
-                },
+        if !conveyor.items.front_at_end() {
+            set_conveyor_blocked(
                 entity,
-                );
-            }
+                conveyor,
+                false,
+                &mut conveyor_graph,
+                &mut blocked_events,
+            );
+            continue;
         }
+        //the end of a line blocks just like a full belt
+        let Some(next_conveyor) = conveyor.connected_conveyor_belt else {
+            set_conveyor_blocked(
+                entity,
+                conveyor,
+                true,
+                &mut conveyor_graph,
+                &mut blocked_events,
+            );
+            continue;
+        };
+        let belt_item = conveyor.items.front().unwrap().clone();
+        commands.trigger_targets(
+            ItemReachedOtherBeltTrigger {
+                belt_item,
+                position: conveyor.position_for_distance(conveyor.items.length()),
+                next_conveyor,
+            },
+            entity,
+        );
     }
 }
 
@@ -65,17 +78,26 @@ pub fn segments_changed(
     trigger: Trigger<ConveyorSegmentsChanged>,
     mut q_conveyor_belts: Query<&mut ConveyorBelt>,
     q_belts: Query<(&Transform, &BeltElement)>,
+    active_q: Query<(), With<Active>>,
     world_grid: Res<WorldGrid>,
-    mut commands: Commands,
+    mut conveyor_graph: ResMut<ConveyorGraph>,
 ) {
     info!("segments have changed");
     let Ok(conveyor_belt) = q_conveyor_belts.get_mut(trigger.entity()) else {
         return;
     };
     let conveyor_belt = conveyor_belt.into_inner();
+    //pieces come and go with every change, switching a piece on or off only updates the count
+    conveyor_belt.inactive_pieces = conveyor_belt
+        .belt_pieces
+        .iter()
+        .filter(|piece| !active_q.contains(piece.entity))
+        .count();
 
-    let segment_count_before = conveyor_belt.segments.len();
+    //items keep their world position, the lane is rebuilt on the new segments
+    let old_items = conveyor_belt.take_items();
     conveyor_belt.segments.clear();
+    conveyor_belt.connected_conveyor_belt = None;
     let segments = &mut conveyor_belt.segments;
     let belt_width_offset = 0.25; // Half of the belt width (0.5 / 2)
 
@@ -83,8 +105,13 @@ pub fn segments_changed(
     let mut current_segment = ConveyorSegment::default();
     let mut previous_belt = None;
     let mut is_first = true;
+    let mut direction_changed = false;
+
+    // A loop runs from the center of the first piece all the way around back to it
+    let is_loop = conveyor_belt.is_loop;
+    let loop_closing_piece = conveyor_belt.belt_pieces.first().filter(|_| is_loop);
 
-    for belt in &conveyor_belt.belt_pieces {
+    for belt in conveyor_belt.belt_pieces.iter().chain(loop_closing_piece) {
         let belt_position = world_grid.grid_to_world(&belt.grid_position);
 
         // Set up the first segment start position if it's the first piece
@@ -92,11 +119,12 @@ pub fn segments_changed(
             // Adjust the start position slightly backward to align with the belt piece's center
             let mut start_position = belt_position;
 
-            if is_first {
+            if is_first && !is_loop {
                 start_position -= belt.grid_rotation.get_direction() * belt_width_offset;
-                is_first = false;
             }
+            is_first = false;
             current_segment.set_start_position(start_position);
+            current_segment.tier = belt.tier;
 
             previous_belt = Some(belt);
             continue;
@@ -106,7 +134,8 @@ pub fn segments_changed(
             world_grid.grid_to_world(&previous_belt.unwrap().grid_position);
 
         // Check if the direction has changed; if so, finalize the current segment
-        if previous_belt.unwrap().grid_rotation != belt.grid_rotation {
+        direction_changed = previous_belt.unwrap().grid_rotation != belt.grid_rotation;
+        if direction_changed {
             // Adjust the end position slightly forward to align with the belt piece's center
             let end_position = previous_belt_position
                 + previous_belt.unwrap().grid_rotation.get_direction() * belt_width_offset * 2.0;
@@ -114,6 +143,14 @@ pub fn segments_changed(
             segments.push(current_segment);
             // Start a new segment
             current_segment = ConveyorSegment::new(belt_position, Vec3::ZERO);
+            current_segment.tier = belt.tier;
+        } else if previous_belt.unwrap().tier != belt.tier {
+            // A different tier moves its items at its own speed, so it gets a segment of its own
+            let boundary = (previous_belt_position + belt_position) * 0.5;
+            current_segment.set_end_position(boundary);
+            segments.push(current_segment);
+            current_segment = ConveyorSegment::new(boundary, belt_position);
+            current_segment.tier = belt.tier;
         } else {
             // Continue the current segment without changing direction
             current_segment.set_end_position(belt_position);
@@ -122,8 +159,13 @@ pub fn segments_changed(
         previous_belt = Some(belt);
     }
 
-    // Finalize the last segment if there's an unfinished one
-    if let Some(previous_belt) = previous_belt {
+    if is_loop {
+        // The segment ending in the first piece is already finished if the loop turns there
+        if !direction_changed {
+            segments.push(current_segment);
+        }
+    } else if let Some(previous_belt) = previous_belt {
+        // Finalize the last segment if there's an unfinished one
         // Adjust the end position of the last segment
         let end_position = world_grid.grid_to_world(&previous_belt.grid_position)
             + previous_belt.grid_rotation.get_direction() * belt_width_offset;
@@ -147,134 +189,134 @@ pub fn segments_changed(
                     let mut connector_segment =
                         ConveyorSegment::new(current_segment.end_position(), transform.translation);
                     connector_segment.is_connector = true;
+                    connector_segment.tier = previous_belt.tier;
 
                     segments.push(connector_segment);
-                    commands
-                        .entity(trigger.entity())
-                        .observe(handle_item_reached_other_belt);
                 }
             }
         }
     }
 
-    let mut item_updates = vec![];
-    let segment_count = conveyor_belt.segments.len();
-    // let mut min_segment = conveyor_belt.segments.len() - 1;
-    for item in &conveyor_belt.items {
-        let position = item.position;
-        let segment_index = conveyor_belt
-            .get_segment_index_for_position(position, true)
-            .expect(&format!(
-                "Failed to get segment index for conveyor {:?} item: {:?}",
-                conveyor_belt.segments, item
-            ));
-
-        let segment: &ConveyorSegment = &conveyor_belt.segments[segment_index];
-        let progress = segment.progress_for_point(item.position);
-
-        if (item.segment_index != segment_index) {
-            let text = format!(
-                "segment index changed for {}->{} p:{}->{} {}->{}",
-                item.segment_index,
-                segment_index,
-                position,
-                segment.position_for_progress(progress),
-                item.segment_progress,
-                progress
-            );
-            warn!("{}", text);
+    let mut placed_items = vec![];
+    for (position, item) in old_items {
+        let distance = conveyor_belt
+            .distance_for_position(position, true)
+            .or_else(|| conveyor_belt.closest_distance_for_position(position));
+        match distance {
+            Some(distance) => placed_items.push((distance, item)),
+            None => {
+                warn!("item {:?} is not on any segment anymore {}", item, position);
+            }
         }
-        item_updates.push((segment_index, progress));
     }
-    //before update check
-    let mut segment_check = conveyor_belt.segments.len();
-    let mut progress_check = 1.0;
-    for (i, item) in conveyor_belt.items.iter().enumerate() {
-        if item.segment_index < segment_check {
-            segment_check = item.segment_index;
-            progress_check = item.segment_progress;
-        }
-
-        if item.segment_index > segment_check {
-            error!(
-                "something went really wrong before update due to segment i:{} {} - {}",
-                i, segment_check, item.segment_index
-            );
-        } else if item.segment_progress > progress_check {
-            error!(
-                "something went really wrong before update due to progress i:{} s:{} {} - {}",
-                i, item.segment_index, progress_check, item.segment_progress
-            );
-        }
-        progress_check = item.segment_progress;
+    let (track, rejected) =
+        BeltTrack::from_items(&conveyor_belt.speed_zones(), is_loop, placed_items);
+    for (distance, item) in rejected {
+        error!("item {:?} does not fit on the belt at {}", item, distance);
     }
+    conveyor_belt.items = track;
+    conveyor_graph.set_downstream(trigger.entity(), conveyor_belt.connected_conveyor_belt);
+}
 
-    for i in 0..item_updates.len() {
-        let mut item = conveyor_belt.items.get_mut(i).unwrap();
-        let update = &item_updates[i];
-        if segment_count != segment_count_before {
-            let message = format!(
-                "{} segment updates changed progress {} -> {}",
-                segment_count - segment_count_before,
-                item.segment_progress,
-                update.1
-            );
-            info!(message);
-        }
-        item.segment_index = update.0;
-        item.segment_progress = update.1;
+pub fn conveyor_removed(
+    trigger: Trigger<OnRemove, ConveyorBelt>,
+    mut conveyor_graph: ResMut<ConveyorGraph>,
+    mut commands: Commands,
+) {
+    //conveyors feeding into the removed one have to look for the belt that replaced it
+    for upstream in conveyor_graph.remove(trigger.entity()) {
+        commands.trigger_targets(ConveyorSegmentsChanged, upstream);
     }
+}
 
-    let mut segment_check = conveyor_belt.segments.len();
-    let mut progress_check = 1.0;
-
-    for (i, item) in conveyor_belt.items.iter().enumerate() {
-        if item.segment_index < segment_check {
-            segment_check = item.segment_index;
-            progress_check = item.segment_progress;
-            continue;
-        }
-
-        if item.segment_index > segment_check {
-            error!(
-                "something went really wrong due to a segment i:{} {} - {}",
-                i, segment_check, item.segment_index
-            );
-        } else if item.segment_progress > progress_check {
-            error!(
-                "something went really wrong due to progress i:{} s:{} {} - {}",
-                i, item.segment_index, progress_check, item.segment_progress
-            );
-        }
+fn set_conveyor_blocked(
+    entity: Entity,
+    conveyor: &mut ConveyorBelt,
+    blocked: bool,
+    conveyor_graph: &mut ConveyorGraph,
+    blocked_events: &mut EventWriter<ConveyorBlockedChanged>,
+) {
+    if conveyor.blocked == blocked {
+        return;
     }
+    conveyor.blocked = blocked;
+    conveyor_graph.set_blocked(entity, blocked);
+    blocked_events.send(ConveyorBlockedChanged {
+        conveyor: entity,
+        blocked,
+    });
 }
 
 pub fn handle_item_reached_other_belt(
     trigger: Trigger<ItemReachedOtherBeltTrigger>,
     mut q_conveyor_belt: Query<&mut ConveyorBelt>,
+    mut conveyor_graph: ResMut<ConveyorGraph>,
+    mut blocked_events: EventWriter<ConveyorBlockedChanged>,
 ) {
-    if trigger.entity() == trigger.event().next_conveyor {
-        let mut conveyor = q_conveyor_belt.get_mut(trigger.entity()).unwrap();
-        let item = &trigger.event().belt_item;
-        // conveyor.remove_item(item);
-        if !conveyor.has_space_at_position(item.position, item.item_width, Some(item.item_entity)) {
-            return;
-        }
-        conveyor.remove_item(item);
-        conveyor.insert_item(item);
-        return;
-    }
-
+    let event = trigger.event();
     let Ok([mut current_conveyor, mut next_conveyor]) =
-        q_conveyor_belt.get_many_mut([trigger.entity(), trigger.event().next_conveyor])
+        q_conveyor_belt.get_many_mut([trigger.entity(), event.next_conveyor])
     else {
+        //the next conveyor is gone or is this one, the front item waits like on a full belt
+        if let Ok(mut current_conveyor) = q_conveyor_belt.get_mut(trigger.entity()) {
+            set_conveyor_blocked(
+                trigger.entity(),
+                &mut current_conveyor,
+                true,
+                &mut conveyor_graph,
+                &mut blocked_events,
+            );
+        }
         return;
     };
 
-    let item = &trigger.event().belt_item;
-    if !next_conveyor.has_space_at_position(item.position, item.item_width, None) {
+    if !current_conveyor.items.front_at_end() {
         return;
     }
-    current_conveyor.remove_item(item);
-    next_conveyor.insert_item(item);
+    //popping the front item opens the gap that lets the whole queue behind it move again
+    let handed_over = next_conveyor.insert_item(event.position, event.belt_item.clone());
+    if handed_over {
+        current_conveyor.items.pop_front();
+    }
+    set_conveyor_blocked(
+        trigger.entity(),
+        &mut current_conveyor,
+        !handed_over,
+        &mut conveyor_graph,
+        &mut blocked_events,
+    );
+}
+
+pub fn rebuild_belt_item_batches_system(
+    mut batches: ResMut<BeltItemBatches>,
+    q_conveyor: Query<(Entity, &ConveyorBelt), Changed<ConveyorBelt>>,
+    mut removed_conveyors: RemovedComponents<ConveyorBelt>,
+) {
+    for entity in removed_conveyors.read() {
+        batches.remove(entity);
+    }
+    for (entity, conveyor) in q_conveyor.iter() {
+        batches.remove(entity);
+        for (position, item) in conveyor.item_positions() {
+            batches
+                .batches
+                .entry(item.item_type)
+                .or_default()
+                .entry(entity)
+                .or_default()
+                .push(position);
+        }
+    }
+}
+
+pub fn draw_belt_items_system(mut painter: ShapePainter, batches: Res<BeltItemBatches>) {
+    painter.set_rotation(Quat::from_rotation_x(TAU * 0.25));
+    painter.hollow = false;
+    for (item_type, conveyors) in batches.batches.iter() {
+        painter.color = item_type.color();
+        for position in conveyors.values().flatten() {
+            painter.transform.translation = *position + Vec3::Y * 0.2;
+            painter.circle(0.05);
+        }
+    }
 }
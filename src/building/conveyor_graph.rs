@@ -0,0 +1,202 @@
+use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+
+/// Connections between conveyors, kept up to date whenever the segments of a conveyor change.
+///
+/// Every conveyor hands its items to at most one other conveyor, but any number of conveyors
+/// can feed into the same one.
+#[derive(Resource, Default, Debug)]
+pub struct ConveyorGraph {
+    downstream: HashMap<Entity, Entity>,
+    upstream: HashMap<Entity, HashSet<Entity>>,
+    blocked: HashSet<Entity>,
+}
+
+impl ConveyorGraph {
+    pub fn set_downstream(&mut self, conveyor: Entity, downstream: Option<Entity>) {
+        if let Some(previous) = self.downstream.remove(&conveyor) {
+            if let Some(feeding) = self.upstream.get_mut(&previous) {
+                feeding.remove(&conveyor);
+            }
+        }
+        if let Some(downstream) = downstream {
+            self.downstream.insert(conveyor, downstream);
+            self.upstream
+                .entry(downstream)
+                .or_default()
+                .insert(conveyor);
+        }
+    }
+
+    ///Removes the conveyor from the graph and returns the conveyors that were feeding into it
+    pub fn remove(&mut self, conveyor: Entity) -> Vec<Entity> {
+        self.set_downstream(conveyor, None);
+        self.blocked.remove(&conveyor);
+        let feeding = self.upstream.remove(&conveyor).unwrap_or_default();
+        for upstream in feeding.iter() {
+            self.downstream.remove(upstream);
+        }
+        feeding.into_iter().collect()
+    }
+
+    ///The conveyor this one hands its items to
+    pub fn downstream(&self, conveyor: Entity) -> Option<Entity> {
+        self.downstream.get(&conveyor).copied()
+    }
+
+    ///All conveyors that directly feed items into this one
+    pub fn upstream(&self, conveyor: Entity) -> impl Iterator<Item = Entity> + '_ {
+        self.upstream
+            .get(&conveyor)
+            .into_iter()
+            .flat_map(|feeding| feeding.iter().copied())
+    }
+
+    ///Conveyors an item passes starting from this one, ends early if the conveyors form a cycle
+    pub fn path(&self, conveyor: Entity) -> Vec<Entity> {
+        let mut path = vec![conveyor];
+        let mut visited = HashSet::new();
+        visited.insert(conveyor);
+        let mut current = conveyor;
+        while let Some(next) = self.downstream(current) {
+            if !visited.insert(next) {
+                break;
+            }
+            path.push(next);
+            current = next;
+        }
+        path
+    }
+
+    ///The conveyor an item on this conveyor ends up on
+    pub fn destination(&self, conveyor: Entity) -> Entity {
+        *self.path(conveyor).last().unwrap()
+    }
+
+    pub fn set_blocked(&mut self, conveyor: Entity, blocked: bool) {
+        if blocked {
+            self.blocked.insert(conveyor);
+        } else {
+            self.blocked.remove(&conveyor);
+        }
+    }
+
+    ///The front item of the conveyor can't leave it
+    pub fn is_blocked(&self, conveyor: Entity) -> bool {
+        self.blocked.contains(&conveyor)
+    }
+
+    pub fn blocked_conveyors(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.blocked.iter().copied()
+    }
+
+    ///The conveyor at the front of the jam this conveyor is stuck in, if it is blocked at all
+    pub fn blocking_conveyor(&self, conveyor: Entity) -> Option<Entity> {
+        self.path(conveyor)
+            .into_iter()
+            .take_while(|c| self.is_blocked(*c))
+            .last()
+    }
+
+    ///All conveyors that are connected to this one, no matter in which direction
+    pub fn connected_line(&self, conveyor: Entity) -> Vec<Entity> {
+        let mut line = vec![];
+        let mut visited = HashSet::new();
+        let mut to_visit = vec![conveyor];
+        while let Some(current) = to_visit.pop() {
+            if !visited.insert(current) {
+                continue;
+            }
+            line.push(current);
+            to_visit.extend(self.downstream(current));
+            to_visit.extend(self.upstream(current));
+        }
+        line
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn conveyors<const N: usize>() -> [Entity; N] {
+        std::array::from_fn(|i| Entity::from_raw(i as u32))
+    }
+
+    #[test]
+    fn set_downstream_moves_the_connection() {
+        let [a, b, c] = conveyors();
+        let mut graph = ConveyorGraph::default();
+        graph.set_downstream(a, Some(b));
+        assert_eq!(graph.downstream(a), Some(b));
+        assert_eq!(graph.upstream(b).collect::<Vec<_>>(), vec![a]);
+
+        graph.set_downstream(a, Some(c));
+        assert_eq!(graph.downstream(a), Some(c));
+        assert_eq!(graph.upstream(b).count(), 0);
+        assert_eq!(graph.upstream(c).collect::<Vec<_>>(), vec![a]);
+
+        graph.set_downstream(a, None);
+        assert_eq!(graph.downstream(a), None);
+        assert_eq!(graph.upstream(c).count(), 0);
+    }
+
+    #[test]
+    fn remove_returns_the_feeding_conveyors() {
+        let [a, b, c, d] = conveyors();
+        let mut graph = ConveyorGraph::default();
+        graph.set_downstream(a, Some(c));
+        graph.set_downstream(b, Some(c));
+        graph.set_downstream(c, Some(d));
+        graph.set_blocked(c, true);
+
+        let mut feeding = graph.remove(c);
+        feeding.sort();
+        assert_eq!(feeding, vec![a, b]);
+        assert_eq!(graph.downstream(a), None);
+        assert_eq!(graph.downstream(b), None);
+        assert_eq!(graph.upstream(d).count(), 0);
+        assert!(!graph.is_blocked(c));
+    }
+
+    #[test]
+    fn path_ends_at_a_cycle() {
+        let [a, b, c] = conveyors();
+        let mut graph = ConveyorGraph::default();
+        graph.set_downstream(a, Some(b));
+        graph.set_downstream(b, Some(c));
+        assert_eq!(graph.path(a), vec![a, b, c]);
+        assert_eq!(graph.destination(a), c);
+
+        graph.set_downstream(c, Some(a));
+        assert_eq!(graph.path(a), vec![a, b, c]);
+    }
+
+    #[test]
+    fn blocking_conveyor_is_the_front_of_the_jam() {
+        let [a, b, c] = conveyors();
+        let mut graph = ConveyorGraph::default();
+        graph.set_downstream(a, Some(b));
+        graph.set_downstream(b, Some(c));
+        assert_eq!(graph.blocking_conveyor(a), None);
+
+        graph.set_blocked(a, true);
+        graph.set_blocked(b, true);
+        assert_eq!(graph.blocking_conveyor(a), Some(b));
+        assert_eq!(graph.blocking_conveyor(c), None);
+    }
+
+    #[test]
+    fn connected_line_follows_both_directions() {
+        let [a, b, c, d, e] = conveyors();
+        let mut graph = ConveyorGraph::default();
+        graph.set_downstream(a, Some(c));
+        graph.set_downstream(b, Some(c));
+        graph.set_downstream(c, Some(d));
+
+        let mut line = graph.connected_line(b);
+        line.sort();
+        assert_eq!(line, vec![a, b, c, d]);
+        assert_eq!(graph.connected_line(e), vec![e]);
+    }
+}
@@ -2,41 +2,106 @@ use self::building_components::*;
 use crate::building::building_components::{
     BuildingPlacedEvent, BuildingRemovedEvent, ConveyorPlacedEvent, Inserter,
 };
+use crate::building::building_definitions::BuildingDefinitions;
+use crate::building::building_history::BuildingHistory;
 use crate::building::building_systems::*;
-use crate::building::conveyor_belt::ConveyorBelt;
-use crate::building::conveyor_belt_systems::{conveyor_system, segments_changed};
+use crate::building::conveyor_belt::{BeltItemBatches, ConveyorBelt, ConveyorBlockedChanged};
+use crate::building::conveyor_belt_systems::{
+    conveyor_removed, conveyor_system, draw_belt_items_system, handle_item_reached_other_belt,
+    rebuild_belt_item_batches_system, segments_changed,
+};
+use crate::building::conveyor_graph::ConveyorGraph;
+use crate::AppState;
 use bevy::prelude::*;
 
+pub mod belt_lane;
 pub mod building_components;
-mod building_systems;
+pub mod building_definitions;
+pub mod building_history;
+pub mod building_systems;
 pub mod conveyor_belt;
 mod conveyor_belt_systems;
+pub mod conveyor_graph;
 
 pub struct BuildingPlugin;
 
 impl Plugin for BuildingPlugin {
     fn build(&self, app: &mut App) {
+        let definitions_dir = std::env::current_dir()
+            .unwrap()
+            .join("assets")
+            .join("buildings");
+
         app
+            .insert_resource(BuildingDefinitions::load(&definitions_dir))
             .add_event::<BuildingPlacedEvent>()
             .add_event::<ConveyorPlacedEvent>()
             .add_event::<BuildingRemovedEvent>()
+            .add_event::<BuildingRotatedEvent>()
+            .add_event::<BuildingMovedEvent>()
+            .add_event::<ConveyorBlockedChanged>()
+            .add_event::<BeltUpgradedEvent>()
             .register_type::<Building>()
             .register_type::<Extractor>()
             .register_type::<BeltElement>()
             .register_type::<ConveyorBelt>()
             .register_type::<Inserter>()
-            .add_systems(Update, place_building_system)
-            .add_systems(PostUpdate, respond_to_conveyor_belt_placement_event)
-            .add_systems(PostUpdate, handle_conveyor_placement_system.after(respond_to_conveyor_belt_placement_event))
-            .add_systems(Update, remove_building_system)
-            .add_systems(Update, respond_to_belt_element_removal.after(remove_building_system))
-            .add_systems(Update, extract_resources_system)
-            .add_systems(Update, inserter_animation_system)
-            // .add_systems(Update, inserter_system)
-            .add_systems(PostUpdate, conveyor_system)
-
-            .add_systems(PostUpdate, destroy_building_system)
+            .register_type::<Lab>()
+            .register_type::<BuildingStatus>()
+            .init_resource::<BeltItemBatches>()
+            .init_resource::<ConveyorGraph>()
+            .init_resource::<BeltLinePlan>()
+            .init_resource::<BuildingHistory>()
+            .init_resource::<MoveTool>()
+            .init_resource::<DeconstructionSelection>()
+            .init_resource::<DisabledMaterial>()
+            .add_systems(
+                Update,
+                (
+                    place_building_system,
+                    belt_line_drag_system,
+                    draw_belt_line_plan_system.after(belt_line_drag_system),
+                    remove_building_system,
+                    draw_deconstruction_selection_system.after(remove_building_system),
+                    undo_redo_system.before(respond_to_belt_element_removal),
+                    respond_to_belt_element_removal.after(remove_building_system),
+                    rotate_building_system,
+                    respond_to_building_rotation.after(rotate_building_system).after(undo_redo_system),
+                    move_building_system,
+                    respond_to_building_move.after(move_building_system).after(undo_redo_system),
+                    draw_move_tool_system.after(move_building_system),
+                    upgrade_belt_system,
+                    respond_to_belt_upgrade.after(upgrade_belt_system),
+                    extract_resources_system,
+                    inserter_animation_system,
+                    (
+                        toggle_active_system,
+                        disabled_tint_system.after(toggle_active_system),
+                        disabled_status_system.after(toggle_active_system),
+                        spawn_status_icon_system,
+                        update_status_icon_system
+                            .after(disabled_status_system),
+                    ).after(extract_resources_system).after(inserter_animation_system),
+                    // inserter_system,
+                ).run_if(in_state(AppState::Game))
+            )
+            .add_systems(
+                PostUpdate,
+                (
+                    respond_to_conveyor_belt_placement_event,
+                    add_building_collider_system,
+                    handle_conveyor_placement_system.after(respond_to_conveyor_belt_placement_event),
+                    conveyor_system,
+                    rebuild_belt_item_batches_system.after(conveyor_system),
+                    draw_belt_items_system.after(rebuild_belt_item_batches_system),
+                    destroy_building_system,
+                    record_building_history_system.before(destroy_building_system),
+                ).run_if(in_state(AppState::Game))
+            )
+            .add_systems(OnExit(AppState::Game), reset_building_system)
             .add_observer(segments_changed)
+            .add_observer(handle_item_reached_other_belt)
+            .add_observer(conveyor_removed)
 
         // .add_systems(Update, test_place_building_system)
 
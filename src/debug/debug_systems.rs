@@ -1,10 +1,11 @@
-use crate::building::building_components::{BeltElement, ConveyorSegment};
+use crate::building::building_components::{BeltElement, BuildingStatus, ConveyorSegment};
 use crate::building::conveyor_belt::ConveyorBelt;
+use crate::building::conveyor_graph::ConveyorGraph;
 use crate::debug::debug_components::*;
 use crate::general::general_components::GeneralAssets;
 use crate::player::player_components::GameCursor;
 use crate::world_grid::world_gird_components::*;
-use crate::MainCamera;
+use crate::{AppState, MainCamera};
 use bevy::color::palettes::css::*;
 use bevy::color::palettes::tailwind::*;
 use bevy::prelude::*;
@@ -44,6 +45,7 @@ pub fn debug_setup(
         },
         DebugText,
         Name::new("Debug Text"),
+        StateScoped(AppState::Game),
     ));
 
     commands.spawn((
@@ -65,6 +67,7 @@ pub fn debug_setup(
         BorderRadius::all(Val::Px(5.0)),
         Name::new("Debug Info Panel"),
         DebugInfoPanel::default(),
+        StateScoped(AppState::Game),
     ));
 }
 
@@ -115,7 +118,18 @@ pub fn debug_draw_conveyors(
     mut shapes: ShapePainter,
     conveyor_q: Query<&ConveyorBelt>,
     world_grid: Res<WorldGrid>,
+    conveyor_graph: Res<ConveyorGraph>,
 ) {
+    //the end of a blocked conveyor is where its front item waits
+    for conveyor in conveyor_q.iter_many(conveyor_graph.blocked_conveyors()) {
+        shapes.transform = Transform::from_translation(
+            world_grid.grid_to_world(&conveyor.end_position()) + Vec3::Y * 0.15,
+        )
+        .with_rotation(Quat::from_rotation_x(TAU * 0.25));
+        shapes.hollow = false;
+        shapes.color = RED.with_alpha(0.5).into();
+        shapes.circle(0.2);
+    }
     for conveyor in conveyor_q.iter() {
         for belt in conveyor.belt_pieces.iter() {
             shapes.transform = Transform::from_translation(
@@ -126,11 +140,11 @@ pub fn debug_draw_conveyors(
             shapes.hollow = true;
             // shapes.color = Color::PURPLE.pastel();
 
-            if belt.grid_position == conveyor.start_position() {
+            if !conveyor.is_loop && belt.grid_position == conveyor.start_position() {
                 shapes.color = Color::BLACK;
                 shapes.circle(0.1);
             }
-            if belt.grid_position == conveyor.end_position() {
+            if !conveyor.is_loop && belt.grid_position == conveyor.end_position() {
                 shapes.color = RED.into();
                 shapes.circle(0.15);
             }
@@ -194,12 +208,15 @@ pub fn hover_selection_system(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn debug_hover_system(
     mut commands: Commands,
     mut info_panel_q: Single<(Entity, &DebugInfoPanel)>,
     general_assets: Res<GeneralAssets>,
+    status_q: Query<&BuildingStatus>,
     belt_q: Query<&BeltElement>,
     conveyor_q: Query<&ConveyorBelt>,
+    conveyor_graph: Res<ConveyorGraph>,
     mut gizmos: Gizmos,
 ) {
     let (entity, info_panel) = info_panel_q.into_inner();
@@ -210,11 +227,30 @@ pub fn debug_hover_system(
         return;
     };
 
-    if let Some(conveyor) = belt_q
+    if let Ok(status) = status_q.get(building_entity) {
+        commands.entity(entity).with_children(|commands| {
+            commands.spawn((
+                Text(format!("status: {}", status.label())),
+                TextFont {
+                    font: general_assets.default_font.clone(),
+                    font_size: 10.0,
+                    ..default()
+                },
+                TextColor(status.color()),
+            ));
+        });
+    }
+
+    if let Some((conveyor_entity, conveyor)) = belt_q
         .get(building_entity)
         .ok()
         .and_then(|belt| belt.conveyor_belt)
-        .and_then(|conveyor_entity| conveyor_q.get(conveyor_entity).ok())
+        .and_then(|conveyor_entity| {
+            conveyor_q
+                .get(conveyor_entity)
+                .ok()
+                .map(|conveyor| (conveyor_entity, conveyor))
+        })
     {
         let up = Vec3::Y * 0.1;
         for segment in &conveyor.segments {
@@ -245,11 +281,14 @@ pub fn debug_hover_system(
 
         let up = Vec3::Y * 0.00;
         let segment_colors = [ORANGE_400, GREEN_400, BLUE_500, PURPLE_600];
-        for item in &conveyor.items {
+        for (position, _) in conveyor.item_positions() {
+            let segment_index = conveyor
+                .get_segment_index_for_position(position, true)
+                .unwrap_or_default();
             gizmos.circle(
-                Isometry3d::new(item.position + up, Quat::from_rotation_x(TAU * 0.25)),
+                Isometry3d::new(position + up, Quat::from_rotation_x(TAU * 0.25)),
                 0.05,
-                segment_colors[item.segment_index % segment_colors.len()],
+                segment_colors[segment_index % segment_colors.len()],
             );
         }
         commands.entity(entity).with_children(|commands| {
@@ -262,7 +301,38 @@ pub fn debug_hover_system(
                 },
             ));
             commands.spawn((
-                Text(format!("segments: {}", conveyor.segments.len())),
+                Text(format!(
+                    "segments: {} loop: {}",
+                    conveyor.segments.len(),
+                    conveyor.is_loop
+                )),
+                TextFont {
+                    font: general_assets.default_font.clone(),
+                    font_size: 10.0,
+                    ..default()
+                },
+            ));
+
+            commands.spawn((
+                Text(format!(
+                    "fed by: {} line: {} ends on: {}",
+                    conveyor_graph.upstream(conveyor_entity).count(),
+                    conveyor_graph.connected_line(conveyor_entity).len(),
+                    conveyor_graph.destination(conveyor_entity)
+                )),
+                TextFont {
+                    font: general_assets.default_font.clone(),
+                    font_size: 10.0,
+                    ..default()
+                },
+            ));
+
+            commands.spawn((
+                Text(format!(
+                    "items: {} speed zones: {}",
+                    conveyor.items.len(),
+                    conveyor.items.zone_count()
+                )),
                 TextFont {
                     font: general_assets.default_font.clone(),
                     font_size: 10.0,
@@ -271,7 +341,13 @@ pub fn debug_hover_system(
             ));
 
             commands.spawn((
-                Text(format!("items: {}", conveyor.items.len())),
+                Text(format!(
+                    "length: {:.2} compressed: {} blocked: {} jammed by: {:?}",
+                    conveyor.items.length(),
+                    conveyor.items.is_compressed(),
+                    conveyor.blocked,
+                    conveyor_graph.blocking_conveyor(conveyor_entity)
+                )),
                 TextFont {
                     font: general_assets.default_font.clone(),
                     font_size: 10.0,
@@ -279,15 +355,11 @@ pub fn debug_hover_system(
                 },
             ));
 
-            for (i, item) in conveyor.items.iter().enumerate() {
+            for (i, (distance, item)) in conveyor.items.iter().enumerate() {
                 commands.spawn((
                     Text(format!(
-                        "i: {:>3} s:{} - p:{:.2} - {} {}",
-                        i,
-                        item.segment_index,
-                        item.segment_progress,
-                        item.position,
-                        item.item_entity
+                        "i: {:>3} d:{:.2} - {:?}",
+                        i, distance, item.item_type
                     )),
                     TextFont {
                         font: general_assets.default_font.clone(),
@@ -1,9 +1,9 @@
 use self::debug_systems::move_debug_text_system;
 use crate::debug::debug_components::CursorDebugTextEvent;
 use crate::debug::debug_systems::*;
-use crate::setup;
-use bevy::app::{App, Plugin, Startup, Update};
-use bevy::prelude::IntoSystemConfigs;
+use crate::AppState;
+use bevy::app::{App, Plugin, Update};
+use bevy::prelude::{in_state, IntoSystemConfigs, OnEnter};
 
 pub mod debug_components;
 mod debug_systems;
@@ -14,13 +14,19 @@ impl Plugin for SmallDebugPlugin {
     fn build(&self, app: &mut App) {
         app
             .add_event::<CursorDebugTextEvent>()
-            .add_systems(Startup, debug_setup.after(setup))
-            .add_systems(Update, move_debug_text_system)
-            .add_systems(Update, change_debug_text_system)
-            .add_systems(Update, cursor_position_debug_system)
-            .add_systems(Update, debug_draw_conveyors)
-            .add_systems(Update, debug_hover_system)
-            .add_systems(Update, hover_selection_system)
+            .add_systems(OnEnter(AppState::Game), debug_setup)
+            .add_systems(
+                Update,
+                (
+                    move_debug_text_system,
+                    change_debug_text_system,
+                    cursor_position_debug_system,
+                    debug_draw_conveyors,
+                    debug_hover_system,
+                    hover_selection_system,
+                )
+                    .run_if(in_state(AppState::Game)),
+            )
 
             // .add_systems(Update, draw_belt_forward)
             ;
@@ -1,11 +1,104 @@
 use crate::building::building_components::BuildingType;
+use avian3d::prelude::PhysicsLayer;
 use bevy::prelude::*;
 
+/// Physics layers, the cursor ray only hits the ground
+#[derive(PhysicsLayer, Default)]
+pub enum GameLayer {
+    #[default]
+    Default,
+    Ground,
+}
+
 #[derive(Component, Default, Reflect)]
 pub struct BuildingButton {
     pub building_type: BuildingType,
 }
 
+#[derive(Component, Default)]
+pub struct PlacementTooltip;
+
+#[derive(Component, Default)]
+pub struct InventoryText;
+
+#[derive(Component, Default)]
+pub struct MainMenuButton;
+
+#[derive(Component, Default)]
+pub struct SimulationSpeedText;
+
+const SPEED_MULTIPLIERS: [f32; 3] = [1.0, 2.0, 4.0];
+
+/// Speed of the simulation, applied to the virtual time that the factory and physics run on
+#[derive(Resource, Debug)]
+pub struct SimulationSpeed {
+    pub paused: bool,
+    pub multiplier: f32,
+    ///advances the paused simulation by a single fixed timestep
+    pub step: bool,
+}
+
+impl Default for SimulationSpeed {
+    fn default() -> Self {
+        Self {
+            paused: false,
+            multiplier: 1.0,
+            step: false,
+        }
+    }
+}
+
+impl SimulationSpeed {
+    pub fn faster(&mut self) {
+        if let Some(multiplier) = SPEED_MULTIPLIERS.iter().find(|m| **m > self.multiplier) {
+            self.multiplier = *multiplier;
+        }
+    }
+
+    pub fn slower(&mut self) {
+        if let Some(multiplier) = SPEED_MULTIPLIERS
+            .iter()
+            .rev()
+            .find(|m| **m < self.multiplier)
+        {
+            self.multiplier = *multiplier;
+        }
+    }
+
+    pub fn label(&self) -> String {
+        if self.paused {
+            "Paused".to_string()
+        } else {
+            format!("{}x", self.multiplier)
+        }
+    }
+}
+
+/// Materials the preview building is tinted with, depending on whether it can be placed
+#[derive(Resource)]
+pub struct PreviewMaterials {
+    pub valid: Handle<StandardMaterial>,
+    pub invalid: Handle<StandardMaterial>,
+}
+
+impl FromWorld for PreviewMaterials {
+    fn from_world(world: &mut World) -> Self {
+        let mut materials = world.resource_mut::<Assets<StandardMaterial>>();
+        PreviewMaterials {
+            valid: materials.add(StandardMaterial {
+                base_color: Color::srgba(0.2, 0.9, 0.3, 0.6),
+                alpha_mode: AlphaMode::Blend,
+                ..default()
+            }),
+            invalid: materials.add(StandardMaterial {
+                base_color: Color::srgba(0.9, 0.2, 0.2, 0.6),
+                alpha_mode: AlphaMode::Blend,
+                ..default()
+            }),
+        }
+    }
+}
+
 #[derive(Resource)]
 pub struct GeneralAssets {
     pub default_font: Handle<Font>,
@@ -1,11 +1,16 @@
 use std::f32::consts::TAU;
 
 use crate::building::building_components::*;
+use crate::building::building_definitions::BuildingDefinitions;
+use crate::building::building_systems::validate_placement;
 use crate::debug::debug_components::CursorPositionDebug;
-use crate::general::general_components::BuildingButton;
-use crate::player::player_components::GameCursor;
+use crate::general::general_components::{
+    BuildingButton, GameLayer, InventoryText, MainMenuButton, PlacementTooltip, PreviewMaterials,
+    SimulationSpeed, SimulationSpeedText,
+};
+use crate::player::player_components::{GameCursor, Inventory};
 use crate::world_grid::world_gird_components::*;
-use crate::MainCamera;
+use crate::{AppState, MainCamera};
 use avian3d::prelude::{SpatialQuery, SpatialQueryFilter};
 use bevy::asset::AssetServer;
 use bevy::color::palettes::css::RED;
@@ -20,9 +25,54 @@ pub fn setup_menu(
     mut commands: Commands,
     camera_query: Query<Entity, With<MainCamera>>,
     asset_server: Res<AssetServer>,
+    definitions: Res<BuildingDefinitions>,
 ) {
     let main_camera = camera_query.get_single().unwrap();
     let font = asset_server.load("fonts/FiraMono-Medium.ttf");
+
+    commands.spawn((
+        TargetCamera(main_camera),
+        Text::default(),
+        TextFont {
+            font: font.clone(),
+            font_size: 12.0,
+            ..default()
+        },
+        Node {
+            position_type: PositionType::Absolute,
+            padding: UiRect::all(Val::Px(4.0)),
+            ..default()
+        },
+        BackgroundColor(Color::srgba(0.1, 0.1, 0.1, 0.8)),
+        BorderRadius::all(Val::Px(3.0)),
+        Visibility::Hidden,
+        PlacementTooltip,
+        Name::new("Placement Tooltip"),
+        StateScoped(AppState::Game),
+    ));
+
+    commands.spawn((
+        TargetCamera(main_camera),
+        Text::default(),
+        TextFont {
+            font: font.clone(),
+            font_size: 14.0,
+            ..default()
+        },
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(5.0),
+            left: Val::Percent(50.0),
+            padding: UiRect::all(Val::Px(4.0)),
+            ..default()
+        },
+        BackgroundColor(Color::srgba(0.1, 0.1, 0.1, 0.8)),
+        BorderRadius::all(Val::Px(3.0)),
+        SimulationSpeedText,
+        Name::new("Simulation Speed"),
+        StateScoped(AppState::Game),
+    ));
+
     // root node
     commands
         .spawn((
@@ -33,6 +83,7 @@ pub fn setup_menu(
                 justify_content: JustifyContent::SpaceBetween,
                 ..default()
             },
+            StateScoped(AppState::Game),
         ))
         .with_children(|parent| {
             parent
@@ -77,6 +128,20 @@ pub fn setup_menu(
                                 Label,
                                 CursorPositionDebug,
                             ));
+                            parent.spawn((
+                                Node {
+                                    margin: UiRect::all(Val::Px(1.)),
+                                    ..default()
+                                },
+                                Text("Inventory".to_owned()),
+                                TextFont {
+                                    font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+                                    font_size: 10.0,
+                                    ..default()
+                                },
+                                Label,
+                                InventoryText,
+                            ));
 
                             parent.spawn(Node {
                                 width: Val::Percent(100.),
@@ -98,9 +163,41 @@ pub fn setup_menu(
                                 Label,
                             ));
 
-                            menu_button(parent, font.clone(), "Extractor", BuildingType::Extractor);
-                            menu_button(parent, font.clone(), "Belt", BuildingType::ConveyorBelt);
-                            menu_button(parent, font, "Inserter", BuildingType::InserterType);
+                            for definition in definitions.sorted() {
+                                menu_button(
+                                    parent,
+                                    font.clone(),
+                                    &definition.name,
+                                    definition.building_type(),
+                                );
+                            }
+
+                            parent
+                                .spawn((
+                                    Button,
+                                    Node {
+                                        width: Val::Px(50.),
+                                        height: Val::Px(30.0),
+                                        margin: UiRect::top(Val::Px(20.0)),
+                                        border: UiRect::all(Val::Px(1.0)),
+                                        justify_content: JustifyContent::Center,
+                                        align_items: AlignItems::Center,
+                                        ..default()
+                                    },
+                                    BorderColor(Color::BLACK),
+                                    BackgroundColor(NORMAL_BUTTON.into()),
+                                    MainMenuButton,
+                                ))
+                                .with_children(|parent| {
+                                    parent.spawn((
+                                        Text("Menu".to_owned()),
+                                        TextFont {
+                                            font,
+                                            font_size: 10.0,
+                                            ..default()
+                                        },
+                                    ));
+                                });
                         });
                 });
         });
@@ -170,6 +267,26 @@ pub fn button_highlight_system(
     }
 }
 
+pub fn main_menu_button_system(
+    interaction_query: Query<&Interaction, (Changed<Interaction>, With<MainMenuButton>)>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    for interaction in interaction_query.iter() {
+        if *interaction == Interaction::Pressed {
+            info!("returning to the main menu");
+            next_state.set(AppState::MainMenu);
+        }
+    }
+}
+
+pub fn reset_general_system(
+    mut game_cursor: ResMut<GameCursor>,
+    mut simulation_speed: ResMut<SimulationSpeed>,
+) {
+    game_cursor.preview_entity = None;
+    *simulation_speed = SimulationSpeed::default();
+}
+
 pub fn update_cursor_system(
     window_query: Query<&Window>,
     camera_query: Query<(&Camera, &GlobalTransform), (With<Camera>, With<MainCamera>)>,
@@ -181,7 +298,8 @@ pub fn update_cursor_system(
     game_cursor.ui_position = window.cursor_position();
     if let Some(cursor_position) = window.cursor_position() {
         let ray = camera.viewport_to_world(camera_transform, cursor_position);
-        let filter = SpatialQueryFilter::default();
+        //buildings and resource nodes would otherwise shift the cursor off the grid
+        let filter = SpatialQueryFilter::from_mask(GameLayer::Ground);
         if let Ok(ray) = ray {
             if let Some(hit) =
                 spatial_query.cast_ray(ray.origin, ray.direction, f32::MAX, true, &filter)
@@ -202,6 +320,7 @@ pub fn building_ui_selection_system(
     >,
     mut game_cursor: ResMut<GameCursor>,
     world_grid: Res<WorldGrid>,
+    definitions: Res<BuildingDefinitions>,
     mut shapes: ShapeCommands,
 ) {
     for (interaction, building_button) in &mut interaction_query {
@@ -215,8 +334,11 @@ pub fn building_ui_selection_system(
                 }
             }
 
-            game_cursor.preview_entity = Building::spawn(
-                building_button.building_type,
+            let Some(definition) = definitions.get(&building_button.building_type) else {
+                continue;
+            };
+            let preview_entity = Building::spawn(
+                definition,
                 game_cursor.world_position.unwrap_or_default(),
                 Quat::default(),
                 world_grid.grid_size,
@@ -224,11 +346,57 @@ pub fn building_ui_selection_system(
                 &mut asset_server,
                 &mut shapes,
             );
-            commands
-                .entity(game_cursor.preview_entity.unwrap())
-                .insert(Preview {});
+            commands.entity(preview_entity).insert(Preview {});
+            game_cursor.preview_entity = Some(preview_entity);
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn pipette_building_system(
+    mut commands: Commands,
+    mut asset_server: ResMut<AssetServer>,
+    input: Res<ButtonInput<KeyCode>>,
+    mut game_cursor: ResMut<GameCursor>,
+    world_grid: Res<WorldGrid>,
+    definitions: Res<BuildingDefinitions>,
+    building_q: Query<(&Building, &Transform), Without<Preview>>,
+    mut shapes: ShapeCommands,
+) {
+    //Q and E turn the free camera, so the pipette has a key of its own
+    if !input.just_pressed(KeyCode::KeyG) {
+        return;
+    }
+    let Some(position) = game_cursor.world_position else {
+        return;
+    };
+    let grid_position = world_grid.grid_position_from_world_position(position);
+    let Some(building_entity) = world_grid.get_building_entity(&grid_position) else {
+        return;
+    };
+    let Ok((building, transform)) = building_q.get(building_entity) else {
+        return;
+    };
+    let Some(definition) = definitions.get(&building.building_type) else {
+        return;
+    };
+
+    if let Some(preview_entity) = game_cursor.preview_entity {
+        if let Some(entity_command) = commands.get_entity(preview_entity) {
+            entity_command.despawn_recursive();
         }
     }
+    let preview_entity = Building::spawn(
+        definition,
+        position,
+        transform.grid_rotation().to_quat(),
+        world_grid.grid_size,
+        &mut commands,
+        &mut asset_server,
+        &mut shapes,
+    );
+    commands.entity(preview_entity).insert(Preview {});
+    game_cursor.preview_entity = Some(preview_entity);
 }
 
 pub fn move_building_preview_with_cursor_system(
@@ -282,3 +450,219 @@ pub fn rotate_preview_item_system(
 
     transform.rotate_y(-TAU * 0.25);
 }
+
+pub fn placement_feedback_system(
+    game_cursor: Res<GameCursor>,
+    world_grid: Res<WorldGrid>,
+    preview_materials: Res<PreviewMaterials>,
+    inventory: Res<Inventory>,
+    definitions: Res<BuildingDefinitions>,
+    preview_q: Query<&Building, With<Preview>>,
+    children_q: Query<&Children>,
+    mut material_q: Query<&mut MeshMaterial3d<StandardMaterial>>,
+    mut tooltip_q: Query<(&mut Text, &mut Visibility), With<PlacementTooltip>>,
+) {
+    let Ok((mut text, mut visibility)) = tooltip_q.get_single_mut() else {
+        return;
+    };
+    let preview = game_cursor
+        .preview_entity
+        .and_then(|entity| preview_q.get(entity).ok().map(|b| (entity, b)));
+    let (Some((preview_entity, building)), Some(position)) = (preview, game_cursor.world_position)
+    else {
+        *visibility = Visibility::Hidden;
+        return;
+    };
+    let grid_position = world_grid.grid_position_from_world_position(position);
+    let placement = validate_placement(
+        &world_grid,
+        &definitions,
+        &building.building_type,
+        grid_position,
+    )
+    .and_then(|_| {
+        if inventory.can_afford(&definitions.cost(&building.building_type)) {
+            Ok(())
+        } else {
+            Err(PlacementError::MissingItems)
+        }
+    });
+
+    //the scene of the preview spawns later, so the tint is applied every frame
+    let material = if placement.is_ok() {
+        &preview_materials.valid
+    } else {
+        &preview_materials.invalid
+    };
+    for entity in children_q.iter_descendants(preview_entity) {
+        if let Ok(mut mesh_material) = material_q.get_mut(entity) {
+            if mesh_material.0 != *material {
+                mesh_material.0 = material.clone();
+            }
+        }
+    }
+
+    match placement {
+        Ok(()) => *visibility = Visibility::Hidden,
+        Err(error) => {
+            text.0 = error.message().to_string();
+            *visibility = Visibility::Inherited;
+        }
+    }
+}
+
+pub fn move_placement_tooltip_system(
+    window_query: Query<&Window>,
+    mut tooltip_q: Query<&mut Node, With<PlacementTooltip>>,
+) {
+    let window = window_query.get_single().unwrap();
+    let Ok(mut node) = tooltip_q.get_single_mut() else {
+        return;
+    };
+    if let Some(cursor_position) = window.cursor_position() {
+        node.left = Val::Px(cursor_position.x + 20.0);
+        node.top = Val::Px(cursor_position.y - 30.0);
+    }
+}
+
+pub fn update_inventory_text_system(
+    inventory: Res<Inventory>,
+    mut text_q: Query<&mut Text, With<InventoryText>>,
+) {
+    let Ok(mut text) = text_q.get_single_mut() else {
+        return;
+    };
+    let mut items = inventory.items.iter().collect::<Vec<_>>();
+    items.sort_by_key(|(item_type, _)| format!("{:?}", item_type));
+    text.0 = items
+        .into_iter()
+        .map(|(item_type, amount)| format!("{:?}: {}", item_type, amount))
+        .collect::<Vec<_>>()
+        .join("\n");
+}
+
+pub fn simulation_speed_input_system(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut simulation_speed: ResMut<SimulationSpeed>,
+) {
+    if keys.just_pressed(KeyCode::KeyP) {
+        simulation_speed.paused = !simulation_speed.paused;
+    }
+    if keys.just_pressed(KeyCode::BracketRight) {
+        simulation_speed.faster();
+    }
+    if keys.just_pressed(KeyCode::BracketLeft) {
+        simulation_speed.slower();
+    }
+    if keys.just_pressed(KeyCode::Period) && simulation_speed.paused {
+        simulation_speed.step = true;
+    }
+}
+
+///The virtual clock drives every timed system and the physics, so pausing it stops the whole factory
+pub fn apply_simulation_speed_system(
+    simulation_speed: Res<SimulationSpeed>,
+    mut time: ResMut<Time<Virtual>>,
+) {
+    if time.relative_speed() != simulation_speed.multiplier {
+        time.set_relative_speed(simulation_speed.multiplier);
+    }
+    if simulation_speed.paused {
+        time.pause();
+    } else {
+        time.unpause();
+    }
+}
+
+///Runs right after the clocks are updated, so the fixed loop and the physics see the step in the same frame
+pub fn step_simulation_system(
+    mut simulation_speed: ResMut<SimulationSpeed>,
+    mut time: ResMut<Time<Virtual>>,
+    fixed_time: Res<Time<Fixed>>,
+) {
+    if !simulation_speed.step {
+        return;
+    }
+    simulation_speed.step = false;
+    //the paused clock does not move by itself, exactly one physics tick is added
+    time.advance_by(fixed_time.timestep());
+}
+
+pub fn update_simulation_speed_text_system(
+    simulation_speed: Res<SimulationSpeed>,
+    mut text_q: Query<&mut Text, With<SimulationSpeedText>>,
+) {
+    let Ok(mut text) = text_q.get_single_mut() else {
+        return;
+    };
+    text.0 = simulation_speed.label();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::time::{TimePlugin, TimeSystem, TimeUpdateStrategy};
+    use std::time::Duration;
+
+    #[derive(Resource, Default)]
+    struct FixedTicks(u32);
+
+    fn count_fixed_ticks_system(mut ticks: ResMut<FixedTicks>) {
+        ticks.0 += 1;
+    }
+
+    fn paused_app() -> App {
+        let mut app = App::new();
+        app.add_plugins(TimePlugin)
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+                40,
+            )))
+            .insert_resource(SimulationSpeed {
+                paused: true,
+                ..default()
+            })
+            .init_resource::<FixedTicks>()
+            .add_systems(First, step_simulation_system.after(TimeSystem))
+            .add_systems(Update, apply_simulation_speed_system)
+            .add_systems(FixedUpdate, count_fixed_ticks_system);
+        for _ in 0..3 {
+            app.update();
+        }
+        app
+    }
+
+    fn virtual_elapsed(app: &App) -> Duration {
+        app.world().resource::<Time<Virtual>>().elapsed()
+    }
+
+    #[test]
+    fn paused_simulation_does_not_move() {
+        let mut app = paused_app();
+        let elapsed = virtual_elapsed(&app);
+        let ticks = app.world().resource::<FixedTicks>().0;
+        for _ in 0..5 {
+            app.update();
+        }
+        assert_eq!(virtual_elapsed(&app), elapsed);
+        assert_eq!(app.world().resource::<FixedTicks>().0, ticks);
+    }
+
+    #[test]
+    fn step_advances_exactly_one_fixed_tick() {
+        let mut app = paused_app();
+        let timestep = app.world().resource::<Time<Fixed>>().timestep();
+        let elapsed = virtual_elapsed(&app);
+        let ticks = app.world().resource::<FixedTicks>().0;
+
+        app.world_mut().resource_mut::<SimulationSpeed>().step = true;
+        app.update();
+        assert_eq!(virtual_elapsed(&app), elapsed + timestep);
+        assert_eq!(app.world().resource::<FixedTicks>().0, ticks + 1);
+        assert!(!app.world().resource::<SimulationSpeed>().step);
+
+        //the step is used up, the simulation stays paused
+        app.update();
+        assert_eq!(virtual_elapsed(&app), elapsed + timestep);
+        assert_eq!(app.world().resource::<FixedTicks>().0, ticks + 1);
+    }
+}
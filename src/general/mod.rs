@@ -1,8 +1,9 @@
-use crate::general::general_components::GeneralAssets;
+use crate::general::general_components::{GeneralAssets, PreviewMaterials, SimulationSpeed};
 use crate::general::general_systems::*;
-use crate::player::player_components::GameCursor;
-use crate::setup;
+use crate::player::player_components::{GameCursor, Inventory};
+use crate::AppState;
 use bevy::prelude::*;
+use bevy::time::TimeSystem;
 
 pub mod general_components;
 mod general_systems;
@@ -12,13 +13,44 @@ pub struct GeneralPlugin;
 impl Plugin for GeneralPlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(GameCursor::default())
-            .add_systems(PostUpdate, update_cursor_system)
             .add_systems(Update, button_highlight_system)
-            .add_systems(Update, building_ui_selection_system)
-            .add_systems(Update, remove_preview_building_system)
-            .add_systems(Update, rotate_preview_item_system)
-            .add_systems(PostUpdate, move_building_preview_with_cursor_system)
-            .add_systems(Startup, setup_menu.after(setup))
-            .init_resource::<GeneralAssets>();
+            .add_systems(
+                PostUpdate,
+                (
+                    update_cursor_system,
+                    move_building_preview_with_cursor_system,
+                    placement_feedback_system.after(update_cursor_system),
+                )
+                    .run_if(in_state(AppState::Game)),
+            )
+            .add_systems(
+                Update,
+                (
+                    building_ui_selection_system,
+                    main_menu_button_system,
+                    remove_preview_building_system,
+                    rotate_preview_item_system,
+                    pipette_building_system,
+                    move_placement_tooltip_system,
+                    update_inventory_text_system.run_if(resource_changed::<Inventory>),
+                    simulation_speed_input_system,
+                    apply_simulation_speed_system.after(simulation_speed_input_system),
+                    update_simulation_speed_text_system
+                        .after(simulation_speed_input_system)
+                        .run_if(resource_changed::<SimulationSpeed>),
+                )
+                    .run_if(in_state(AppState::Game)),
+            )
+            .add_systems(
+                First,
+                step_simulation_system
+                    .after(TimeSystem)
+                    .run_if(in_state(AppState::Game)),
+            )
+            .add_systems(OnEnter(AppState::Game), setup_menu)
+            .add_systems(OnExit(AppState::Game), reset_general_system)
+            .init_resource::<GeneralAssets>()
+            .init_resource::<PreviewMaterials>()
+            .init_resource::<SimulationSpeed>();
     }
 }
@@ -1,16 +1,27 @@
+mod alerts;
+mod blueprint;
 mod building;
 mod debug;
 mod general;
+mod main_menu;
 mod player;
+mod research;
 mod save_and_load;
+mod statistics;
 pub mod utilities;
 mod world_grid;
 
+use crate::alerts::AlertsPlugin;
+use crate::blueprint::BlueprintPlugin;
 use crate::building::BuildingPlugin;
 use crate::debug::SmallDebugPlugin;
+use crate::general::general_components::GameLayer;
 use crate::general::GeneralPlugin;
+use crate::main_menu::MainMenuPlugin;
 use crate::player::PlayerPlugin;
+use crate::research::ResearchPlugin;
 use crate::save_and_load::SaveLoadAsterophagePlugin;
+use crate::statistics::StatisticsPlugin;
 use crate::world_grid::WorldGridPlugin;
 use avian3d::prelude::*;
 use bevy::log::{tracing_subscriber, LogPlugin};
@@ -24,8 +35,8 @@ use tracing_subscriber::Layer;
 
 #[derive(States, Debug, Clone, Copy, Eq, PartialEq, Hash, Default)]
 pub enum AppState {
-    MainMenu,
     #[default]
+    MainMenu,
     Game,
     GameOver,
 }
@@ -57,19 +68,38 @@ fn main() {
         .add_plugins(RngPlugin::new().with_rng_seed(135))
         // .add_plugins(bevy_framepace::FramepacePlugin)
         .init_state::<AppState>()
+        .enable_state_scoped_entities::<AppState>()
         .add_systems(Startup, setup)
+        .add_systems(OnEnter(AppState::Game), setup_world)
+        .add_plugins(MainMenuPlugin)
         .add_plugins(GeneralPlugin)
         .add_plugins(PlayerPlugin)
         .add_plugins(WorldGridPlugin)
         .add_plugins(BuildingPlugin)
         .add_plugins(SmallDebugPlugin)
         .add_plugins(SaveLoadAsterophagePlugin)
+        .add_plugins(BlueprintPlugin)
+        .add_plugins(StatisticsPlugin)
+        .add_plugins(ResearchPlugin)
+        .add_plugins(AlertsPlugin)
         // .insert_resource(Time::<Fixed>::from_hz(60.0))
         .run();
 }
 
-/// set up a simple 3D scene
-fn setup(
+fn setup(mut commands: Commands) {
+    let _eye = Vec3::new(-0.2, 2.5, 5.0);
+    let _target = Vec3::default();
+
+    // camera, kept between the menu and the game
+    commands.spawn((
+        Camera3d::default(),
+        Transform::from_xyz(-2.0, 2.5, 5.0).looking_at(Vec3::ZERO, Vec3::Y),
+        MainCamera {},
+    ));
+}
+
+/// set up a simple 3D scene, it is removed again when the game is left
+fn setup_world(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
@@ -87,8 +117,10 @@ fn setup(
             ..default()
         },
         Collider::cuboid(1.0, 1.0, 1.0),
+        CollisionLayers::new(GameLayer::Ground, LayerMask::ALL),
         RigidBody::Static,
         Name::new("Floor"),
+        StateScoped(AppState::Game),
     ));
 
     let cube_mesh = meshes.add(Cuboid::default());
@@ -125,6 +157,7 @@ fn setup(
                 Collider::cuboid(collider_size, collider_size, collider_size),
                 Name::new("cube"),
                 Mass(10.0),
+                StateScoped(AppState::Game),
             ))
             .id();
 
@@ -132,23 +165,16 @@ fn setup(
     }
 
     // light
-    commands.spawn(PointLightBundle {
-        point_light: PointLight {
-            intensity: 1_000_000.0 * 0.5,
-            shadows_enabled: true,
+    commands.spawn((
+        PointLightBundle {
+            point_light: PointLight {
+                intensity: 1_000_000.0 * 0.5,
+                shadows_enabled: true,
+                ..default()
+            },
+            transform: Transform::from_xyz(4.0, 20.0, 4.0),
             ..default()
         },
-        transform: Transform::from_xyz(4.0, 20.0, 4.0),
-        ..default()
-    });
-
-    let _eye = Vec3::new(-0.2, 2.5, 5.0);
-    let _target = Vec3::default();
-
-    // camera
-    commands.spawn((
-        Camera3d::default(),
-        Transform::from_xyz(-2.0, 2.5, 5.0).looking_at(Vec3::ZERO, Vec3::Y),
-        MainCamera {},
+        StateScoped(AppState::Game),
     ));
 }
@@ -0,0 +1,76 @@
+use crate::AppState;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(SubStates, Debug, Clone, Copy, Eq, PartialEq, Hash, Default)]
+#[source(AppState = AppState::MainMenu)]
+pub enum MenuScreen {
+    #[default]
+    Main,
+    Settings,
+}
+
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub enum MenuAction {
+    NewGame,
+    LoadSlot(usize),
+    Settings,
+    ToggleGrid,
+    ToggleFullscreen,
+    Back,
+    Quit,
+}
+
+/// Text of a menu button, settings show their current value in it
+#[derive(Component, Debug)]
+pub struct MenuLabel {
+    pub action: MenuAction,
+}
+
+/// Seed typed in for a new game, a random one is used if it is empty
+#[derive(Component, Default)]
+pub struct SeedInput {
+    pub text: String,
+}
+
+/// How the game is started once the main menu is left
+#[derive(Resource, Default, Debug)]
+pub struct GameStart {
+    pub load_slot: Option<usize>,
+}
+
+/// Settings of the settings screen, kept in `settings.ron`
+#[derive(Resource, Serialize, Deserialize, Debug, Clone)]
+pub struct GameSettings {
+    pub show_grid: bool,
+    pub fullscreen: bool,
+}
+
+impl Default for GameSettings {
+    fn default() -> Self {
+        Self {
+            show_grid: true,
+            fullscreen: false,
+        }
+    }
+}
+
+fn on_off(value: bool) -> &'static str {
+    if value {
+        "On"
+    } else {
+        "Off"
+    }
+}
+
+impl MenuLabel {
+    pub fn text(&self, settings: &GameSettings) -> Option<String> {
+        match self.action {
+            MenuAction::ToggleGrid => Some(format!("Grid: {}", on_off(settings.show_grid))),
+            MenuAction::ToggleFullscreen => {
+                Some(format!("Fullscreen: {}", on_off(settings.fullscreen)))
+            }
+            _ => None,
+        }
+    }
+}
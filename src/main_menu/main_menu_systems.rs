@@ -0,0 +1,276 @@
+use crate::main_menu::main_menu_components::*;
+use crate::save_and_load::components::{LoadFromSaveSlot, SaveSlots};
+use crate::world_grid::world_gird_components::ResourceNoiseSettings;
+use crate::{AppState, MainCamera};
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::input::ButtonState;
+use bevy::prelude::*;
+use bevy::window::{MonitorSelection, WindowMode};
+use bevy_persistent::Persistent;
+use bevy_turborand::prelude::*;
+
+const MENU_BUTTON: Color = Color::srgb(0.15, 0.15, 0.15);
+const MAX_SEED_DIGITS: usize = 5;
+
+fn menu_root(commands: &mut Commands, main_camera: Entity, screen: MenuScreen) -> Entity {
+    commands
+        .spawn((
+            TargetCamera(main_camera),
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(8.0),
+                ..default()
+            },
+            BackgroundColor(Color::srgb(0.08, 0.08, 0.08)),
+            StateScoped(screen),
+            Name::new("Main Menu"),
+        ))
+        .id()
+}
+
+fn menu_text(parent: &mut ChildBuilder, font: Handle<Font>, text: &str, font_size: f32) {
+    parent.spawn((
+        Text(text.to_owned()),
+        TextFont {
+            font,
+            font_size,
+            ..default()
+        },
+    ));
+}
+
+fn menu_button(parent: &mut ChildBuilder, font: Handle<Font>, text: &str, action: MenuAction) {
+    parent
+        .spawn((
+            Button,
+            Node {
+                width: Val::Px(200.0),
+                height: Val::Px(36.0),
+                border: UiRect::all(Val::Px(1.0)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            BorderColor(Color::BLACK),
+            BackgroundColor(MENU_BUTTON),
+            action,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text(text.to_owned()),
+                TextFont {
+                    font,
+                    font_size: 14.0,
+                    ..default()
+                },
+                MenuLabel { action },
+            ));
+        });
+}
+
+pub fn spawn_main_menu(
+    mut commands: Commands,
+    camera_query: Query<Entity, With<MainCamera>>,
+    asset_server: Res<AssetServer>,
+    save_slots: Res<Persistent<SaveSlots>>,
+) {
+    let main_camera = camera_query.get_single().unwrap();
+    let font = asset_server.load("fonts/FiraMono-Medium.ttf");
+    let root = menu_root(&mut commands, main_camera, MenuScreen::Main);
+
+    let mut slots = save_slots.slots.keys().copied().collect::<Vec<_>>();
+    slots.sort();
+    let last_slot = save_slots
+        .last_slot
+        .filter(|slot| save_slots.slots.contains_key(slot));
+
+    commands.entity(root).with_children(|parent| {
+        menu_text(parent, font.clone(), "Asterophage", 32.0);
+
+        parent
+            .spawn(Node {
+                column_gap: Val::Px(8.0),
+                align_items: AlignItems::Center,
+                ..default()
+            })
+            .with_children(|parent| {
+                menu_text(parent, font.clone(), "Seed", 14.0);
+                parent.spawn((
+                    Node {
+                        width: Val::Px(120.0),
+                        height: Val::Px(24.0),
+                        border: UiRect::all(Val::Px(1.0)),
+                        padding: UiRect::horizontal(Val::Px(4.0)),
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    BorderColor(Color::WHITE),
+                    Text::default(),
+                    TextFont {
+                        font: font.clone(),
+                        font_size: 14.0,
+                        ..default()
+                    },
+                    SeedInput::default(),
+                ));
+            });
+        menu_button(parent, font.clone(), "New Game", MenuAction::NewGame);
+
+        match last_slot {
+            Some(slot) => menu_button(parent, font.clone(), "Continue", MenuAction::LoadSlot(slot)),
+            None => menu_text(parent, font.clone(), "No save to continue", 12.0),
+        }
+        for slot in slots {
+            menu_button(
+                parent,
+                font.clone(),
+                &format!("Load Slot {}", slot),
+                MenuAction::LoadSlot(slot),
+            );
+        }
+
+        menu_button(parent, font.clone(), "Settings", MenuAction::Settings);
+        menu_button(parent, font, "Quit", MenuAction::Quit);
+    });
+}
+
+pub fn spawn_settings_menu(
+    mut commands: Commands,
+    camera_query: Query<Entity, With<MainCamera>>,
+    asset_server: Res<AssetServer>,
+    settings: Res<Persistent<GameSettings>>,
+) {
+    let main_camera = camera_query.get_single().unwrap();
+    let font = asset_server.load("fonts/FiraMono-Medium.ttf");
+    let root = menu_root(&mut commands, main_camera, MenuScreen::Settings);
+
+    commands.entity(root).with_children(|parent| {
+        menu_text(parent, font.clone(), "Settings", 24.0);
+        for action in [MenuAction::ToggleGrid, MenuAction::ToggleFullscreen] {
+            let text = MenuLabel { action }.text(&settings).unwrap_or_default();
+            menu_button(parent, font.clone(), &text, action);
+        }
+        menu_button(parent, font, "Back", MenuAction::Back);
+    });
+}
+
+pub fn seed_input_system(
+    mut keyboard_events: EventReader<KeyboardInput>,
+    mut seed_q: Query<(&mut SeedInput, &mut Text)>,
+) {
+    let Ok((mut seed_input, mut text)) = seed_q.get_single_mut() else {
+        return;
+    };
+    for event in keyboard_events.read() {
+        if event.state != ButtonState::Pressed {
+            continue;
+        }
+        match &event.logical_key {
+            Key::Backspace => {
+                seed_input.text.pop();
+            }
+            Key::Character(character) => {
+                //only digits, the seed has to fit the noise
+                for c in character.chars().filter(|c| c.is_ascii_digit()) {
+                    if seed_input.text.len() < MAX_SEED_DIGITS {
+                        seed_input.text.push(c);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    if text.0 != seed_input.text {
+        text.0 = seed_input.text.clone();
+    }
+}
+
+pub fn menu_action_system(
+    interaction_query: Query<(&Interaction, &MenuAction), Changed<Interaction>>,
+    seed_q: Query<&SeedInput>,
+    save_slots: Res<Persistent<SaveSlots>>,
+    mut settings: ResMut<Persistent<GameSettings>>,
+    mut resource_settings: ResMut<ResourceNoiseSettings>,
+    mut game_start: ResMut<GameStart>,
+    mut rng: ResMut<GlobalRng>,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut next_screen: ResMut<NextState<MenuScreen>>,
+    mut app_exit: EventWriter<AppExit>,
+) {
+    for (interaction, action) in interaction_query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        match *action {
+            MenuAction::NewGame => {
+                let seed = seed_q
+                    .get_single()
+                    .ok()
+                    .and_then(|seed_input| seed_input.text.parse().ok())
+                    .unwrap_or_else(|| rng.u32(0..10u32.pow(MAX_SEED_DIGITS as u32)));
+                info!("starting a new game with seed {}", seed);
+                resource_settings.seed = seed;
+                game_start.load_slot = None;
+                next_state.set(AppState::Game);
+            }
+            MenuAction::LoadSlot(slot) => {
+                let Some(save_slot) = save_slots.slots.get(&slot) else {
+                    continue;
+                };
+                info!("continuing from save slot {}", slot);
+                resource_settings.seed = save_slot.seed;
+                game_start.load_slot = Some(slot);
+                next_state.set(AppState::Game);
+            }
+            MenuAction::Settings => next_screen.set(MenuScreen::Settings),
+            MenuAction::Back => next_screen.set(MenuScreen::Main),
+            MenuAction::ToggleGrid => settings
+                .update(|settings| settings.show_grid = !settings.show_grid)
+                .expect("Updating the settings failed"),
+            MenuAction::ToggleFullscreen => settings
+                .update(|settings| settings.fullscreen = !settings.fullscreen)
+                .expect("Updating the settings failed"),
+            MenuAction::Quit => {
+                app_exit.send(AppExit::Success);
+            }
+        }
+    }
+}
+
+pub fn update_menu_labels_system(
+    settings: Res<Persistent<GameSettings>>,
+    mut label_q: Query<(&MenuLabel, &mut Text)>,
+) {
+    for (label, mut text) in label_q.iter_mut() {
+        if let Some(label_text) = label.text(&settings) {
+            text.0 = label_text;
+        }
+    }
+}
+
+pub fn apply_window_settings_system(
+    settings: Res<Persistent<GameSettings>>,
+    mut window_query: Query<&mut Window>,
+) {
+    let Ok(mut window) = window_query.get_single_mut() else {
+        return;
+    };
+    window.mode = if settings.fullscreen {
+        WindowMode::BorderlessFullscreen(MonitorSelection::Current)
+    } else {
+        WindowMode::Windowed
+    };
+}
+
+pub fn start_game_system(
+    mut game_start: ResMut<GameStart>,
+    mut load_event: EventWriter<LoadFromSaveSlot>,
+) {
+    if let Some(slot_id) = game_start.load_slot.take() {
+        load_event.send(LoadFromSaveSlot { slot_id });
+    }
+}
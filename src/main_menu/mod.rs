@@ -0,0 +1,45 @@
+use crate::main_menu::main_menu_components::{GameSettings, GameStart, MenuScreen};
+use crate::main_menu::main_menu_systems::*;
+use crate::AppState;
+use bevy::prelude::*;
+use bevy_persistent::prelude::*;
+
+pub mod main_menu_components;
+mod main_menu_systems;
+
+pub struct MainMenuPlugin;
+
+impl Plugin for MainMenuPlugin {
+    fn build(&self, app: &mut App) {
+        let persistence_dir = std::env::current_dir().unwrap().join("assets");
+
+        app.insert_resource(
+            Persistent::<GameSettings>::builder()
+                .name("Settings")
+                .format(StorageFormat::RonPrettyWithStructNames)
+                .path(persistence_dir.join("settings.ron"))
+                .default(GameSettings::default())
+                .build()
+                .expect("failed to initialize the settings"),
+        )
+        .init_resource::<GameStart>()
+        .add_sub_state::<MenuScreen>()
+        .enable_state_scoped_entities::<MenuScreen>()
+        .add_systems(OnEnter(MenuScreen::Main), spawn_main_menu)
+        .add_systems(OnEnter(MenuScreen::Settings), spawn_settings_menu)
+        .add_systems(
+            Update,
+            (
+                seed_input_system,
+                menu_action_system,
+                update_menu_labels_system,
+            )
+                .run_if(in_state(AppState::MainMenu)),
+        )
+        .add_systems(
+            Update,
+            apply_window_settings_system.run_if(resource_changed::<Persistent<GameSettings>>),
+        )
+        .add_systems(OnEnter(AppState::Game), start_game_system);
+    }
+}
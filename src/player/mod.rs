@@ -1,6 +1,8 @@
-use bevy::prelude::*;
-use crate::AppState;
+use crate::player::player_components::{CameraFocus, GameCamera, Inventory};
 use crate::player::player_systems::*;
+use crate::AppState;
+use avian3d::prelude::*;
+use bevy::prelude::*;
 
 pub mod player_components;
 mod player_systems;
@@ -10,18 +12,26 @@ pub struct PlayerPlugin;
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
         app
+            .init_resource::<Inventory>()
+            .init_resource::<CameraFocus>()
+            .init_resource::<GameCamera>()
             .add_systems(OnEnter(AppState::Game),spawn_player)
+            .add_systems(OnExit(AppState::Game),reset_player_system)
             .add_systems(
                 Update,(
                     move_player,
-                    move_camera_system.after(move_player),
+                    manual_mining_system,
+                    draw_mining_progress_system.after(manual_mining_system),
+                    camera_input_system.after(move_player),
+                    move_camera_system.after(camera_input_system),
                     move_light_system.after(move_player),
                     // shoot,
                     life_time_system,
                     bullet_collisions_system,
                 ).run_if(in_state(AppState::Game))
             )
+            .add_systems(PostProcessCollisions, kinematic_controller_collisions)
             // .add_systems(PostUpdate, paint_target)
         ;
     }
-}
\ No newline at end of file
+}
@@ -1,12 +1,62 @@
+use crate::building::building_components::ItemType;
+use crate::world_grid::world_gird_components::GridPosition;
+use crate::AppState;
 use avian3d::prelude::*;
 use bevy::color::palettes::css::PURPLE;
 use bevy::prelude::*;
+use bevy::utils::HashMap;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::TAU;
 
 #[derive(Component, Default)]
 pub struct Player {
     pub local_aim_target: Vec3,
 }
 
+/// Movement of the kinematic player body, speeds are in units per second
+#[derive(Component)]
+pub struct CharacterController {
+    pub max_speed: f32,
+    ///how fast the player speeds up while a direction is held
+    pub acceleration: f32,
+    ///how fast the player slows down once no direction is held
+    pub friction: f32,
+}
+
+impl Default for CharacterController {
+    fn default() -> Self {
+        Self {
+            max_speed: 2.0,
+            acceleration: 16.0,
+            friction: 12.0,
+        }
+    }
+}
+
+const MINING_TIME: f32 = 1.0;
+const MINING_COOLDOWN: f32 = 0.4;
+
+/// The player mining a resource node by hand
+#[derive(Component)]
+pub struct ManualMining {
+    pub target: Option<GridPosition>,
+    pub progress: Timer,
+    ///pause after each mined item
+    pub cooldown: Timer,
+}
+
+impl Default for ManualMining {
+    fn default() -> Self {
+        let mut cooldown = Timer::from_seconds(MINING_COOLDOWN, TimerMode::Once);
+        cooldown.tick(cooldown.duration());
+        Self {
+            target: None,
+            progress: Timer::from_seconds(MINING_TIME, TimerMode::Once),
+            cooldown,
+        }
+    }
+}
+
 #[derive(Component, Default)]
 pub struct Bullet {}
 
@@ -45,6 +95,7 @@ impl Bullet {
                 LifeTime { time_left: 5.0 },
                 GravityScale(0.0),
                 Owner { entity: owner },
+                StateScoped(AppState::Game),
             ))
             .id()
     }
@@ -56,3 +107,144 @@ pub struct GameCursor {
     pub world_position: Option<Vec3>,
     pub preview_entity: Option<Entity>,
 }
+
+/// Point the camera looks at instead of the player, until the player moves again
+#[derive(Resource, Default, Debug)]
+pub struct CameraFocus {
+    pub target: Option<Vec3>,
+}
+
+pub const MIN_ZOOM: f32 = 10.0;
+pub const MAX_ZOOM: f32 = 50.0;
+
+/// Where the camera looks from, it always stays at a quarter turn so the grid keeps its directions
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CameraView {
+    pub center: Vec3,
+    ///quarter turns around the up axis
+    pub quarter_turns: i32,
+    pub zoom: f32,
+}
+
+impl Default for CameraView {
+    fn default() -> Self {
+        Self {
+            center: Vec3::ZERO,
+            quarter_turns: 0,
+            zoom: MIN_ZOOM,
+        }
+    }
+}
+
+impl CameraView {
+    pub fn rotation(&self) -> Quat {
+        Quat::from_rotation_y(TAU * 0.25 * self.quarter_turns as f32)
+    }
+
+    pub fn rotate(&mut self, quarter_turns: i32) {
+        self.quarter_turns = (self.quarter_turns + quarter_turns).rem_euclid(4);
+    }
+
+    pub fn camera_transform(&self) -> Transform {
+        let offset = self.rotation() * Vec3::new(0.0, self.zoom, -3.0);
+        Transform::from_translation(self.center + offset).looking_at(self.center, Vec3::Y)
+    }
+}
+
+pub const CAMERA_BOOKMARK_KEYS: [KeyCode; 4] = [KeyCode::F1, KeyCode::F2, KeyCode::F3, KeyCode::F4];
+
+/// The camera follows the player, or flies on its own in free mode
+#[derive(Resource, Default, Debug)]
+pub struct GameCamera {
+    pub free: bool,
+    ///in follow mode only the zoom is used, the centre is the player
+    pub view: CameraView,
+    ///views stored with shift and the F-keys, one per key, they are kept in the save slot
+    pub bookmarks: [Option<CameraView>; 4],
+}
+
+const STARTING_YELLOW_BILE: u32 = 50;
+
+/// Items the player carries, buildings are paid from it
+#[derive(Resource, Debug, Clone, Serialize, Deserialize)]
+pub struct Inventory {
+    pub items: HashMap<ItemType, u32>,
+}
+
+impl Default for Inventory {
+    fn default() -> Self {
+        Self {
+            items: HashMap::from([(ItemType::YellowBile, STARTING_YELLOW_BILE)]),
+        }
+    }
+}
+
+impl Inventory {
+    pub fn count(&self, item_type: ItemType) -> u32 {
+        self.items.get(&item_type).copied().unwrap_or(0)
+    }
+
+    pub fn add(&mut self, item_type: ItemType, amount: u32) {
+        *self.items.entry(item_type).or_default() += amount;
+    }
+
+    pub fn can_afford(&self, cost: &[(ItemType, u32)]) -> bool {
+        cost.iter()
+            .all(|(item_type, amount)| self.count(*item_type) >= *amount)
+    }
+
+    ///Takes the items out of the inventory, nothing is taken if one of them is missing
+    pub fn spend(&mut self, cost: &[(ItemType, u32)]) -> bool {
+        if !self.can_afford(cost) {
+            return false;
+        }
+        for (item_type, amount) in cost {
+            *self.items.entry(*item_type).or_default() -= amount;
+        }
+        true
+    }
+
+    pub fn refund(&mut self, cost: &[(ItemType, u32)]) {
+        for (item_type, amount) in cost {
+            self.add(*item_type, *amount);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spending_takes_nothing_if_an_item_is_missing() {
+        let mut inventory = Inventory::default();
+        let cost = [(ItemType::YellowBile, STARTING_YELLOW_BILE + 1)];
+        assert!(!inventory.can_afford(&cost));
+        assert!(!inventory.spend(&cost));
+        assert_eq!(inventory.count(ItemType::YellowBile), STARTING_YELLOW_BILE);
+    }
+
+    #[test]
+    fn deconstruction_refunds_the_cost() {
+        let mut inventory = Inventory::default();
+        let cost = [(ItemType::YellowBile, 20)];
+        assert!(inventory.spend(&cost));
+        assert!(inventory.spend(&cost));
+        assert_eq!(inventory.count(ItemType::YellowBile), 10);
+        assert!(!inventory.can_afford(&cost));
+
+        inventory.refund(&cost);
+        assert_eq!(inventory.count(ItemType::YellowBile), 30);
+    }
+
+    #[test]
+    fn picked_up_items_are_added() {
+        let mut inventory = Inventory {
+            items: HashMap::new(),
+        };
+        assert_eq!(inventory.count(ItemType::YellowBile), 0);
+        inventory.add(ItemType::YellowBile, 1);
+        inventory.add(ItemType::YellowBile, 2);
+        assert_eq!(inventory.count(ItemType::YellowBile), 3);
+    }
+}
@@ -1,13 +1,16 @@
 use crate::player::player_components::*;
-use crate::MainCamera;
+use crate::statistics::statistics_components::ItemProducedEvent;
+use crate::world_grid::components::yellow_bile::{mine_resource_node, YellowBileResource};
+use crate::world_grid::world_gird_components::WorldGrid;
+use crate::{AppState, MainCamera};
 use avian3d::prelude::*;
-use bevy::color::palettes::css::ORANGE;
-use bevy::input::mouse::MouseWheel;
+use bevy::color::palettes::css::{GRAY, ORANGE, YELLOW};
+use bevy::input::mouse::{MouseMotion, MouseWheel};
 use bevy::prelude::*;
 use bevy_vector_shapes::prelude::*;
 use std::f32::consts::TAU;
 
-pub const PLAYER_SPEED: f32 = 2.0;
+pub const MINING_REACH: f32 = 1.5;
 
 pub fn spawn_player(
     mut commands: Commands,
@@ -22,20 +25,31 @@ pub fn spawn_player(
         MeshMaterial3d(materials.add(StandardMaterial::from(Color::srgb(0.8, 0.7, 0.6)))),
         Transform::from_xyz(0.0, 0.25, 0.0),
         Player::default(),
-        Collider::cuboid(0.25, 0.25, 0.25),
+        ManualMining::default(),
+        CharacterController::default(),
+        RigidBody::Kinematic,
+        Collider::cuboid(0.5, 0.5, 0.5),
         Name::new("Player"),
+        StateScoped(AppState::Game),
     ));
 }
 
+///The body is moved by the physics, so the player pauses with the game like everything else on the virtual clock
 pub fn move_player(
     keyboard_input: Res<ButtonInput<KeyCode>>,
     game_cursor: Res<GameCursor>,
-    mut player_query: Query<(&mut Transform, &mut Player)>,
+    mut player_query: Query<(
+        &Transform,
+        &mut Player,
+        &CharacterController,
+        &mut LinearVelocity,
+    )>,
+    mut camera_focus: ResMut<CameraFocus>,
     time: Res<Time>,
 ) {
     let mut direction = Vec3::ZERO;
 
-    if let Ok((mut transform, mut player)) = player_query.get_single_mut() {
+    if let Ok((transform, mut player, controller, mut velocity)) = player_query.get_single_mut() {
         if keyboard_input.pressed(KeyCode::ArrowLeft) || keyboard_input.pressed(KeyCode::KeyA) {
             direction += Vec3::X;
         }
@@ -51,36 +65,216 @@ pub fn move_player(
 
         if direction.length_squared() > 0.0 {
             direction = direction.normalize();
+            camera_focus.target = None;
         }
 
-        transform.translation += direction * PLAYER_SPEED * time.delta_secs();
+        //the body speeds up towards the target velocity and slows down by friction without input
+        let target_velocity = direction * controller.max_speed;
+        let rate = if direction == Vec3::ZERO {
+            controller.friction
+        } else {
+            controller.acceleration
+        };
+        let horizontal_velocity = Vec3::new(velocity.x, 0.0, velocity.z);
+        let change =
+            (target_velocity - horizontal_velocity).clamp_length_max(rate * time.delta_secs());
+        velocity.0 = horizontal_velocity + change;
+
         player.local_aim_target =
             transform.transform_point(game_cursor.world_position.unwrap_or_default());
     }
 }
 
-pub fn move_camera_system(
-    mut cameras: Query<&mut Transform, (With<Camera>, With<MainCamera>, Without<Player>)>,
-    player_query: Query<&Transform, (With<Player>, Without<Camera>)>,
+///Pushes the kinematic player out of everything it overlaps and stops it from moving into it,
+///dynamic bodies get pushed away by the physics instead
+pub fn kinematic_controller_collisions(
+    collisions: Res<Collisions>,
+    bodies: Query<&RigidBody>,
+    collider_parents: Query<&ColliderParent, Without<Sensor>>,
+    mut controller_q: Query<
+        (&mut Position, &Rotation, &mut LinearVelocity),
+        (With<RigidBody>, With<CharacterController>),
+    >,
+) {
+    for contacts in collisions.iter() {
+        let Ok([collider_parent1, collider_parent2]) =
+            collider_parents.get_many([contacts.entity1, contacts.entity2])
+        else {
+            continue;
+        };
+        let (controller_entity, other_entity, is_first) =
+            if controller_q.contains(collider_parent1.get()) {
+                (collider_parent1.get(), collider_parent2.get(), true)
+            } else if controller_q.contains(collider_parent2.get()) {
+                (collider_parent2.get(), collider_parent1.get(), false)
+            } else {
+                continue;
+            };
+        let Ok((mut position, rotation, mut velocity)) = controller_q.get_mut(controller_entity)
+        else {
+            continue;
+        };
+        let is_other_dynamic = bodies.get(other_entity).is_ok_and(|rb| rb.is_dynamic());
+
+        for manifold in contacts.manifolds.iter() {
+            let normal = if is_first {
+                -manifold.global_normal1(rotation)
+            } else {
+                -manifold.global_normal2(rotation)
+            };
+            let mut deepest_penetration = f32::MIN;
+            for contact in manifold.contacts.iter() {
+                if contact.penetration > 0.0 {
+                    position.0 += normal * contact.penetration;
+                }
+                deepest_penetration = deepest_penetration.max(contact.penetration);
+            }
+            if is_other_dynamic || deepest_penetration <= 0.0 {
+                continue;
+            }
+            //only the part of the velocity that goes into the obstacle is removed, so the player slides along it
+            let projection = velocity.dot(normal);
+            if projection < 0.0 {
+                velocity.0 -= projection * normal;
+            }
+        }
+    }
+}
+
+const EDGE_PAN_MARGIN: f32 = 10.0;
+///ground the free camera covers per second at the edge of the window, relative to its zoom
+const EDGE_PAN_SPEED: f32 = 1.5;
+const DRAG_PAN_SPEED: f32 = 0.002;
+
+///Switches between the follow and the free camera and moves the free one,
+///it runs on real time so a paused game can still be looked around in
+#[allow(clippy::too_many_arguments)]
+pub fn camera_input_system(
+    keys: Res<ButtonInput<KeyCode>>,
+    mouse_buttons: Res<ButtonInput<MouseButton>>,
     mut mouse_wheel: EventReader<MouseWheel>,
-    mut zoom_level: Local<f32>,
+    mut mouse_motion: EventReader<MouseMotion>,
+    time: Res<Time<Real>>,
+    window_query: Query<&Window>,
+    game_cursor: Res<GameCursor>,
+    player_query: Query<&Transform, With<Player>>,
+    mut camera_focus: ResMut<CameraFocus>,
+    mut game_camera: ResMut<GameCamera>,
 ) {
-    for ev in mouse_wheel.read() {
-        *zoom_level += ev.y;
+    let player_position = player_query
+        .get_single()
+        .map(|transform| transform.translation)
+        .unwrap_or_default();
+    let follow_center = camera_focus.target.unwrap_or(player_position);
+    let ctrl = keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    let shift = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+
+    if keys.just_pressed(KeyCode::KeyC) && !ctrl {
+        game_camera.free = !game_camera.free;
+        if game_camera.free {
+            //the free camera starts where the follow camera looked
+            game_camera.view.center = follow_center;
+            info!("free camera");
+        } else {
+            game_camera.view.quarter_turns = 0;
+            info!("camera follows the player");
+        }
     }
-    *zoom_level = zoom_level.clamp(10.0, 50.0);
 
-    if let Ok(player_transform) = player_query.get_single() {
-        for mut c in cameras.iter_mut() {
-            let look_target = player_transform.translation - player_transform.forward() * 3.0
-                + player_transform.up() * *zoom_level;
-            c.translation = look_target;
-            c.look_at(player_transform.translation, Vec3::Y);
-            c.rotate_around(
-                player_transform.translation,
-                Quat::from_rotation_y(TAU * 0.5),
-            )
+    for (i, key) in CAMERA_BOOKMARK_KEYS.iter().enumerate() {
+        if !keys.just_pressed(*key) {
+            continue;
+        }
+        if shift {
+            let mut view = game_camera.view;
+            if !game_camera.free {
+                view.center = follow_center;
+            }
+            game_camera.bookmarks[i] = Some(view);
+            info!("stored camera bookmark {}", i + 1);
+        } else if let Some(view) = game_camera.bookmarks[i] {
+            game_camera.free = true;
+            game_camera.view = view;
+        }
+    }
+
+    let scroll = mouse_wheel.read().map(|ev| ev.y).sum::<f32>();
+    let motion = mouse_motion.read().map(|ev| ev.delta).sum::<Vec2>();
+    let old_zoom = game_camera.view.zoom;
+    let zoom = (old_zoom + scroll).clamp(MIN_ZOOM, MAX_ZOOM);
+    game_camera.view.zoom = zoom;
+
+    if !game_camera.free {
+        return;
+    }
+    let view = &mut game_camera.view;
+    if let Some(target) = camera_focus.target.take() {
+        view.center = target;
+    }
+
+    //quarter turns keep the grid directions lined up with the screen
+    if !ctrl {
+        if keys.just_pressed(KeyCode::KeyQ) {
+            view.rotate(1);
+        }
+        if keys.just_pressed(KeyCode::KeyE) {
+            view.rotate(-1);
+        }
+    }
+
+    //the point under the cursor stays in place while zooming
+    if zoom != old_zoom {
+        if let Some(cursor_position) = game_cursor.world_position {
+            let anchor = Vec3::new(cursor_position.x, view.center.y, cursor_position.z);
+            view.center = anchor + (view.center - anchor) * (zoom / old_zoom);
+        }
+    }
+
+    //the camera looks along +z before it is turned
+    let forward = view.rotation() * Vec3::Z;
+    let right = view.rotation() * Vec3::NEG_X;
+
+    let mut edge_pan = Vec2::ZERO;
+    if let (Ok(window), Some(cursor)) = (window_query.get_single(), game_cursor.ui_position) {
+        if cursor.x < EDGE_PAN_MARGIN {
+            edge_pan.x -= 1.0;
+        }
+        if cursor.x > window.width() - EDGE_PAN_MARGIN {
+            edge_pan.x += 1.0;
         }
+        if cursor.y < EDGE_PAN_MARGIN {
+            edge_pan.y += 1.0;
+        }
+        if cursor.y > window.height() - EDGE_PAN_MARGIN {
+            edge_pan.y -= 1.0;
+        }
+    }
+    let edge_distance = view.zoom * EDGE_PAN_SPEED * time.delta_secs();
+    view.center += (right * edge_pan.x + forward * edge_pan.y) * edge_distance;
+
+    //the ground sticks to the mouse while it is dragged
+    if mouse_buttons.pressed(MouseButton::Middle) {
+        let drag_distance = view.zoom * DRAG_PAN_SPEED;
+        view.center += (-right * motion.x + forward * motion.y) * drag_distance;
+    }
+}
+
+pub fn move_camera_system(
+    mut cameras: Query<&mut Transform, (With<Camera>, With<MainCamera>, Without<Player>)>,
+    player_query: Query<&Transform, (With<Player>, Without<Camera>)>,
+    camera_focus: Res<CameraFocus>,
+    game_camera: Res<GameCamera>,
+) {
+    let mut view = game_camera.view;
+    if !game_camera.free {
+        let Ok(player_transform) = player_query.get_single() else {
+            return;
+        };
+        view.center = camera_focus.target.unwrap_or(player_transform.translation);
+        view.quarter_turns = 0;
+    }
+    for mut c in cameras.iter_mut() {
+        *c = view.camera_transform();
     }
 }
 
@@ -184,3 +378,104 @@ pub fn move_light_system(
         }
     }
 }
+
+///Mining produces items, so it is part of the simulation and pauses with it
+pub fn manual_mining_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    game_cursor: Res<GameCursor>,
+    mut world_grid: ResMut<WorldGrid>,
+    mut inventory: ResMut<Inventory>,
+    mut node_q: Query<&mut YellowBileResource>,
+    mut player_query: Query<(&Transform, &mut ManualMining), With<Player>>,
+    mut item_produced_event: EventWriter<ItemProducedEvent>,
+) {
+    let Ok((transform, mut mining)) = player_query.get_single_mut() else {
+        return;
+    };
+    mining.cooldown.tick(time.delta());
+
+    let target = game_cursor
+        .world_position
+        .filter(|_| keyboard_input.pressed(KeyCode::KeyF))
+        .map(|position| world_grid.grid_position_from_world_position(position))
+        .filter(|grid_position| {
+            let distance = world_grid
+                .grid_to_world(grid_position)
+                .xz()
+                .distance(transform.translation.xz());
+            distance <= MINING_REACH
+        })
+        .filter(|grid_position| {
+            world_grid
+                .get_cell(grid_position)
+                .is_some_and(|cell| cell.resource_node.is_some())
+        });
+    //letting go of the key or pointing at another node starts over
+    if target != mining.target {
+        mining.target = target;
+        mining.progress.reset();
+    }
+    let Some(grid_position) = target else {
+        return;
+    };
+    if !mining.cooldown.finished() {
+        return;
+    }
+    mining.progress.tick(time.delta());
+    if !mining.progress.finished() {
+        return;
+    }
+    mining.progress.reset();
+    mining.cooldown.reset();
+    if let Some(item_type) =
+        mine_resource_node(&mut commands, &mut world_grid, &mut node_q, &grid_position)
+    {
+        inventory.add(item_type, 1);
+        item_produced_event.send(ItemProducedEvent {
+            item_type,
+            amount: 1,
+        });
+    }
+}
+
+pub fn draw_mining_progress_system(
+    mut painter: ShapePainter,
+    world_grid: Res<WorldGrid>,
+    player_query: Query<&ManualMining, With<Player>>,
+) {
+    let Ok(mining) = player_query.get_single() else {
+        return;
+    };
+    let Some(grid_position) = mining.target else {
+        return;
+    };
+    let position = world_grid.grid_to_world(&grid_position) + Vec3::Y * 0.15;
+    let radius = world_grid.grid_size * 0.4;
+    painter.transform =
+        Transform::from_translation(position).with_rotation(Quat::from_rotation_x(TAU * 0.25));
+    painter.hollow = true;
+    painter.thickness = 0.04;
+
+    //the ring empties during the cooldown and fills up while mining
+    let (color, fraction) = if mining.cooldown.finished() {
+        (YELLOW, mining.progress.fraction())
+    } else {
+        (GRAY, mining.cooldown.fraction_remaining())
+    };
+    painter.color = GRAY.with_alpha(0.3).into();
+    painter.circle(radius);
+    painter.color = color.into();
+    painter.arc(radius, 0.0, TAU * fraction);
+}
+
+pub fn reset_player_system(
+    mut inventory: ResMut<Inventory>,
+    mut camera_focus: ResMut<CameraFocus>,
+    mut game_camera: ResMut<GameCamera>,
+) {
+    *inventory = Inventory::default();
+    *camera_focus = CameraFocus::default();
+    *game_camera = GameCamera::default();
+}
@@ -0,0 +1,33 @@
+use crate::research::research_components::*;
+use crate::research::research_systems::*;
+use crate::AppState;
+use bevy::prelude::*;
+
+pub mod research_components;
+mod research_systems;
+
+pub struct ResearchPlugin;
+
+impl Plugin for ResearchPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<TechTree>()
+            .init_asset_loader::<TechTreeLoader>()
+            .init_resource::<Research>()
+            .add_systems(Startup, load_tech_tree)
+            .add_systems(OnEnter(AppState::Game), spawn_research_panel)
+            .add_systems(OnExit(AppState::Game), reset_research_system)
+            .add_systems(
+                Update,
+                (
+                    toggle_research_panel_system,
+                    tech_button_system,
+                    lab_system,
+                    update_research_panel_system
+                        .after(tech_button_system)
+                        .after(lab_system),
+                    update_build_menu_system.after(lab_system),
+                )
+                    .run_if(in_state(AppState::Game)),
+            );
+    }
+}
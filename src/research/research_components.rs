@@ -0,0 +1,255 @@
+use crate::building::building_components::{BuildingType, ItemType};
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, LoadContext};
+use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+use serde::Deserialize;
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct TechDefinition {
+    pub id: String,
+    pub name: String,
+    ///techs that have to be researched first
+    #[serde(default)]
+    pub prerequisites: Vec<String>,
+    ///items the labs have to consume
+    pub cost: Vec<(ItemType, u32)>,
+    pub unlocks: Vec<BuildingType>,
+}
+
+/// All techs, loaded from `assets/tech_tree.tech.ron`
+#[derive(Asset, TypePath, Deserialize, Debug)]
+pub struct TechTree {
+    pub techs: Vec<TechDefinition>,
+}
+
+impl TechTree {
+    pub fn get(&self, id: &str) -> Option<&TechDefinition> {
+        self.techs.iter().find(|tech| tech.id == id)
+    }
+
+    ///Techs that are not researched yet but have all prerequisites researched
+    pub fn available<'a>(
+        &'a self,
+        research: &'a Research,
+    ) -> impl Iterator<Item = &'a TechDefinition> {
+        self.techs.iter().filter(|tech| {
+            !research.unlocked.contains(&tech.id)
+                && tech
+                    .prerequisites
+                    .iter()
+                    .all(|prerequisite| research.unlocked.contains(prerequisite))
+        })
+    }
+}
+
+#[derive(Resource)]
+pub struct TechTreeHandle(pub Handle<TechTree>);
+
+#[derive(Default)]
+pub struct TechTreeLoader;
+
+impl AssetLoader for TechTreeLoader {
+    type Asset = TechTree;
+    type Settings = ();
+    type Error = std::io::Error;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &Self::Settings,
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        ron::de::from_bytes::<TechTree>(&bytes)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["tech.ron"]
+    }
+}
+
+/// Researched techs and the items the labs already handed in
+#[derive(Resource, Default, Debug)]
+pub struct Research {
+    pub unlocked: HashSet<String>,
+    pub current: Option<String>,
+    ///delivered items per tech, switching the research keeps them
+    pub progress: HashMap<String, HashMap<ItemType, u32>>,
+}
+
+impl Research {
+    pub fn delivered(&self, id: &str, item_type: ItemType) -> u32 {
+        self.progress
+            .get(id)
+            .and_then(|items| items.get(&item_type))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    ///Items of this type the tech still needs
+    pub fn missing(&self, tech: &TechDefinition, item_type: ItemType) -> u32 {
+        tech.cost
+            .iter()
+            .filter(|(cost_item_type, _)| *cost_item_type == item_type)
+            .map(|(_, amount)| amount.saturating_sub(self.delivered(&tech.id, item_type)))
+            .sum()
+    }
+
+    pub fn deliver(&mut self, id: &str, item_type: ItemType) {
+        *self
+            .progress
+            .entry(id.to_owned())
+            .or_default()
+            .entry(item_type)
+            .or_default() += 1;
+    }
+
+    pub fn is_complete(&self, tech: &TechDefinition) -> bool {
+        tech.cost
+            .iter()
+            .all(|(item_type, amount)| self.delivered(&tech.id, *item_type) >= *amount)
+    }
+
+    ///Buildings that no tech unlocks are available from the start
+    pub fn is_building_unlocked(
+        &self,
+        tech_tree: Option<&TechTree>,
+        building_type: &BuildingType,
+    ) -> bool {
+        let Some(tech_tree) = tech_tree else {
+            return true;
+        };
+        let mut unlocking_techs = tech_tree
+            .techs
+            .iter()
+            .filter(|tech| tech.unlocks.contains(building_type))
+            .peekable();
+        unlocking_techs.peek().is_none()
+            || unlocking_techs.any(|tech| self.unlocked.contains(&tech.id))
+    }
+}
+
+#[derive(Component)]
+pub struct ResearchPanelRoot;
+
+#[derive(Component)]
+pub struct ResearchProgressText;
+
+///Column the buttons of the available techs are spawned into
+#[derive(Component)]
+pub struct ResearchTechList;
+
+#[derive(Component, Debug, Clone)]
+pub struct TechButton {
+    pub id: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tech(id: &str, prerequisites: &[&str], cost: u32, unlocks: &[&str]) -> TechDefinition {
+        TechDefinition {
+            id: id.to_owned(),
+            name: id.to_owned(),
+            prerequisites: prerequisites.iter().map(|p| p.to_string()).collect(),
+            cost: vec![(ItemType::YellowBile, cost)],
+            unlocks: unlocks.iter().map(|id| BuildingType::new(id)).collect(),
+        }
+    }
+
+    fn tech_tree() -> TechTree {
+        TechTree {
+            techs: vec![
+                tech("logistics", &[], 10, &["fast_conveyor_belt"]),
+                tech(
+                    "advanced_logistics",
+                    &["logistics"],
+                    20,
+                    &["express_conveyor_belt"],
+                ),
+            ],
+        }
+    }
+
+    fn available(tech_tree: &TechTree, research: &Research) -> Vec<String> {
+        tech_tree
+            .available(research)
+            .map(|tech| tech.id.clone())
+            .collect()
+    }
+
+    #[test]
+    fn buildings_unlock_with_their_tech() {
+        let tech_tree = tech_tree();
+        let mut research = Research::default();
+        let fast = BuildingType::new("fast_conveyor_belt");
+        let express = BuildingType::new("express_conveyor_belt");
+        //nothing unlocks the plain belt, and without a tree everything can be built
+        assert!(
+            research.is_building_unlocked(Some(&tech_tree), &BuildingType::new("conveyor_belt"))
+        );
+        assert!(research.is_building_unlocked(None, &express));
+        assert!(!research.is_building_unlocked(Some(&tech_tree), &fast));
+
+        research.unlocked.insert("logistics".to_owned());
+        assert!(research.is_building_unlocked(Some(&tech_tree), &fast));
+        assert!(!research.is_building_unlocked(Some(&tech_tree), &express));
+    }
+
+    #[test]
+    fn techs_become_available_after_their_prerequisites() {
+        let tech_tree = tech_tree();
+        let mut research = Research::default();
+        assert_eq!(available(&tech_tree, &research), vec!["logistics"]);
+
+        research.unlocked.insert("logistics".to_owned());
+        assert_eq!(available(&tech_tree, &research), vec!["advanced_logistics"]);
+
+        research.unlocked.insert("advanced_logistics".to_owned());
+        assert!(available(&tech_tree, &research).is_empty());
+    }
+
+    #[test]
+    fn delivered_items_count_towards_the_cost() {
+        let tech_tree = tech_tree();
+        let logistics = tech_tree.get("logistics").unwrap();
+        let mut research = Research::default();
+        assert_eq!(research.missing(logistics, ItemType::YellowBile), 10);
+
+        for _ in 0..4 {
+            research.deliver("logistics", ItemType::YellowBile);
+        }
+        assert_eq!(research.missing(logistics, ItemType::YellowBile), 6);
+        assert!(!research.is_complete(logistics));
+
+        //items delivered past the cost are not missing anymore
+        for _ in 0..8 {
+            research.deliver("logistics", ItemType::YellowBile);
+        }
+        assert_eq!(research.missing(logistics, ItemType::YellowBile), 0);
+        assert!(research.is_complete(logistics));
+        assert_eq!(
+            research.missing(
+                tech_tree.get("advanced_logistics").unwrap(),
+                ItemType::YellowBile
+            ),
+            20
+        );
+    }
+
+    #[test]
+    fn tech_tree_asset_refers_to_its_own_techs() {
+        let tech_tree: TechTree =
+            ron::de::from_str(include_str!("../../assets/tech_tree.tech.ron")).unwrap();
+        for tech in tech_tree.techs.iter() {
+            for prerequisite in tech.prerequisites.iter() {
+                assert!(tech_tree.get(prerequisite).is_some(), "{prerequisite}");
+            }
+        }
+        assert!(tech_tree.available(&Research::default()).next().is_some());
+    }
+}
@@ -0,0 +1,276 @@
+use crate::building::building_components::{Active, BeltElement, BuildingStatus, Lab};
+use crate::building::conveyor_belt::ConveyorBelt;
+use crate::general::general_components::BuildingButton;
+use crate::research::research_components::*;
+use crate::statistics::statistics_components::ItemConsumedEvent;
+use crate::world_grid::world_gird_components::WorldGrid;
+use crate::{AppState, MainCamera};
+use bevy::prelude::*;
+
+pub fn load_tech_tree(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(TechTreeHandle(asset_server.load("tech_tree.tech.ron")));
+}
+
+pub fn spawn_research_panel(
+    mut commands: Commands,
+    camera_query: Query<Entity, With<MainCamera>>,
+    asset_server: Res<AssetServer>,
+) {
+    let main_camera = camera_query.get_single().unwrap();
+    let font = asset_server.load("fonts/FiraMono-Medium.ttf");
+
+    commands
+        .spawn((
+            TargetCamera(main_camera),
+            Node {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(5.0),
+                right: Val::Px(5.0),
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(4.0),
+                padding: UiRect::all(Val::Px(5.0)),
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.1, 0.1, 0.1, 0.9)),
+            BorderRadius::all(Val::Px(5.0)),
+            Visibility::Hidden,
+            ResearchPanelRoot,
+            StateScoped(AppState::Game),
+            Name::new("Research Panel"),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::default(),
+                TextFont {
+                    font,
+                    font_size: 10.0,
+                    ..default()
+                },
+                ResearchProgressText,
+            ));
+            parent.spawn((
+                Node {
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Val::Px(2.0),
+                    ..default()
+                },
+                ResearchTechList,
+            ));
+        });
+}
+
+pub fn toggle_research_panel_system(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut panel_q: Query<&mut Visibility, With<ResearchPanelRoot>>,
+) {
+    if !keys.just_pressed(KeyCode::KeyT) {
+        return;
+    }
+    for mut visibility in panel_q.iter_mut() {
+        *visibility = match *visibility {
+            Visibility::Hidden => Visibility::Inherited,
+            _ => Visibility::Hidden,
+        };
+    }
+}
+
+pub fn tech_button_system(
+    interaction_query: Query<(&Interaction, &TechButton), Changed<Interaction>>,
+    mut research: ResMut<Research>,
+) {
+    for (interaction, button) in interaction_query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        info!("researching {}", button.id);
+        research.current = Some(button.id.clone());
+    }
+}
+
+///Rebuilds the tech buttons whenever other techs become available
+pub fn update_research_panel_system(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    research: Res<Research>,
+    tech_trees: Res<Assets<TechTree>>,
+    tech_tree_handle: Res<TechTreeHandle>,
+    list_q: Query<Entity, With<ResearchTechList>>,
+    mut text_q: Query<&mut Text, With<ResearchProgressText>>,
+    mut shown_techs: Local<(Option<Entity>, Vec<String>)>,
+) {
+    let Some(tech_tree) = tech_trees.get(&tech_tree_handle.0) else {
+        return;
+    };
+    let Ok(list) = list_q.get_single() else {
+        return;
+    };
+
+    let current = research.current.as_ref().and_then(|id| tech_tree.get(id));
+    if let Ok(mut text) = text_q.get_single_mut() {
+        text.0 = match current {
+            Some(tech) => {
+                let mut lines = vec![format!("Researching {}", tech.name)];
+                for (item_type, amount) in tech.cost.iter() {
+                    lines.push(format!(
+                        "{:?}: {}/{}",
+                        item_type,
+                        research.delivered(&tech.id, *item_type).min(*amount),
+                        amount
+                    ));
+                }
+                lines.join("\n")
+            }
+            None => "Pick a research".to_owned(),
+        };
+    }
+
+    let available = tech_tree.available(&research).collect::<Vec<_>>();
+    let available_ids = available
+        .iter()
+        .map(|tech| tech.id.clone())
+        .collect::<Vec<_>>();
+    //the list is only rebuilt when it changed, so the buttons keep their interaction state
+    let shown = (Some(list), available_ids);
+    if *shown_techs == shown {
+        return;
+    }
+    *shown_techs = shown;
+
+    let font = asset_server.load("fonts/FiraMono-Medium.ttf");
+    commands.entity(list).despawn_descendants();
+    commands.entity(list).with_children(|parent| {
+        for tech in available {
+            let cost = tech
+                .cost
+                .iter()
+                .map(|(item_type, amount)| format!("{} {:?}", amount, item_type))
+                .collect::<Vec<_>>()
+                .join(", ");
+            parent
+                .spawn((
+                    Button,
+                    Node {
+                        width: Val::Px(200.0),
+                        padding: UiRect::all(Val::Px(3.0)),
+                        border: UiRect::all(Val::Px(1.0)),
+                        ..default()
+                    },
+                    BorderColor(Color::BLACK),
+                    BackgroundColor(Color::srgb(0.15, 0.15, 0.15)),
+                    TechButton {
+                        id: tech.id.clone(),
+                    },
+                ))
+                .with_children(|parent| {
+                    parent.spawn((
+                        Text(format!("{}\n{}", tech.name, cost)),
+                        TextFont {
+                            font: font.clone(),
+                            font_size: 10.0,
+                            ..default()
+                        },
+                    ));
+                });
+        }
+    });
+}
+
+///Labs take the front item of every belt that ends at them, as long as the current research needs it
+pub fn lab_system(
+    time: Res<Time>,
+    world_grid: Res<WorldGrid>,
+    tech_trees: Res<Assets<TechTree>>,
+    tech_tree_handle: Res<TechTreeHandle>,
+    mut research: ResMut<Research>,
+    mut lab_q: Query<(&mut Lab, &mut BuildingStatus, &Transform), With<Active>>,
+    belt_q: Query<&BeltElement>,
+    mut conveyor_q: Query<&mut ConveyorBelt>,
+    mut item_consumed_event: EventWriter<ItemConsumedEvent>,
+) {
+    let Some(tech_tree) = tech_trees.get(&tech_tree_handle.0) else {
+        return;
+    };
+    let Some(tech) = research
+        .current
+        .as_ref()
+        .and_then(|id| tech_tree.get(id))
+        .cloned()
+    else {
+        for (_, mut status, _) in lab_q.iter_mut() {
+            status.set_if_neq(BuildingStatus::Idle);
+        }
+        return;
+    };
+
+    for (mut lab, mut status, transform) in lab_q.iter_mut() {
+        lab.timer.tick(time.delta());
+        if !lab.timer.finished() {
+            continue;
+        }
+        let mut new_status = BuildingStatus::Idle;
+        let grid_position = world_grid.grid_position_from_world_position(transform.translation);
+        for p in grid_position.get_all_surrounding_positions() {
+            let Some(conveyor_entity) = world_grid
+                .get_building_entity(&p)
+                .and_then(|entity| belt_q.get(entity).ok())
+                .and_then(|belt| belt.conveyor_belt)
+            else {
+                continue;
+            };
+            let Ok(mut conveyor) = conveyor_q.get_mut(conveyor_entity) else {
+                continue;
+            };
+            let feeds_lab = conveyor.belt_pieces.last().is_some_and(|piece| {
+                piece.grid_position == p && piece.relative_forward_position() == grid_position
+            });
+            if conveyor.is_loop || !feeds_lab || !conveyor.items.front_at_end() {
+                continue;
+            }
+            let Some(item_type) = conveyor.items.front().map(|item| item.item_type) else {
+                continue;
+            };
+            if research.missing(&tech, item_type) == 0 {
+                continue;
+            }
+            conveyor.items.pop_front();
+            new_status = BuildingStatus::Working;
+            research.deliver(&tech.id, item_type);
+            item_consumed_event.send(ItemConsumedEvent {
+                item_type,
+                amount: 1,
+            });
+        }
+        status.set_if_neq(new_status);
+    }
+
+    if research.is_complete(&tech) {
+        info!("researched {}", tech.name);
+        research.unlocked.insert(tech.id.clone());
+        research.progress.remove(&tech.id);
+        research.current = None;
+    }
+}
+
+///The build menu only shows buildings that are unlocked
+pub fn update_build_menu_system(
+    research: Res<Research>,
+    tech_trees: Res<Assets<TechTree>>,
+    tech_tree_handle: Res<TechTreeHandle>,
+    mut button_q: Query<(&BuildingButton, &mut Node)>,
+) {
+    let tech_tree = tech_trees.get(&tech_tree_handle.0);
+    for (button, mut node) in button_q.iter_mut() {
+        let display = if research.is_building_unlocked(tech_tree, &button.building_type) {
+            Display::Flex
+        } else {
+            Display::None
+        };
+        if node.display != display {
+            node.display = display;
+        }
+    }
+}
+
+pub fn reset_research_system(mut research: ResMut<Research>) {
+    *research = Research::default();
+}
@@ -1,7 +1,9 @@
 use crate::building::building_components::BuildingType;
+use crate::player::player_components::{CameraView, Inventory};
 use bevy::prelude::*;
 use bevy::utils::HashMap;
 use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
 
 #[derive(Serialize, Deserialize, Debug, Default, Clone)]
 pub struct PlacedBuilding {
@@ -9,6 +11,13 @@ pub struct PlacedBuilding {
     pub position: Vec3,
     pub rotation: Quat,
     pub size: f32,
+    ///switched off buildings stay off after loading
+    #[serde(default = "default_active")]
+    pub active: bool,
+}
+
+fn default_active() -> bool {
+    true
 }
 
 // #[derive(Serialize, Deserialize, Debug, Default)]
@@ -16,14 +25,32 @@ pub struct PlacedBuilding {
 //     pub building: Vec<PlacedBuilding>,
 // }
 
+/// Buildings of the loaded save that still have to be placed, one per frame
+#[derive(Resource, Default)]
+pub struct BuildingLoadQueue(pub VecDeque<PlacedBuilding>);
+
 #[derive(Serialize, Deserialize, Debug, Default)]
 pub struct SaveSlot {
     pub buildings: Vec<PlacedBuilding>,
+    ///seed of the world the buildings were placed in
+    #[serde(default)]
+    pub seed: u32,
+    ///ids of the researched techs
+    #[serde(default)]
+    pub unlocked_techs: Vec<String>,
+    ///older saves start with the inventory of a new game
+    #[serde(default)]
+    pub inventory: Inventory,
+    #[serde(default)]
+    pub camera_bookmarks: [Option<CameraView>; 4],
 }
 
 #[derive(Resource, Serialize, Deserialize, Default)]
 pub struct SaveSlots {
     pub slots: HashMap<usize, SaveSlot>,
+    ///slot that was saved to last, the main menu continues from it
+    #[serde(default)]
+    pub last_slot: Option<usize>,
 }
 
 #[derive(Event, Debug)]
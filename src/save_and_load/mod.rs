@@ -1,9 +1,11 @@
 use crate::save_and_load::components::*;
 use crate::save_and_load::systems::*;
+use crate::utilities::utility_methods::convert_legacy_building_types;
+use crate::AppState;
 use bevy::prelude::*;
 use bevy_persistent::prelude::*;
 
-mod components;
+pub mod components;
 mod systems;
 
 pub struct SaveLoadAsterophagePlugin;
@@ -12,20 +14,29 @@ impl Plugin for SaveLoadAsterophagePlugin {
     fn build(&self, app: &mut App) {
         let persistence_dir = std::env::current_dir().unwrap().join("assets");
         println!("we have found folder {:?}", persistence_dir);
+        let save_slots_path = persistence_dir.join("save_slots.ron");
+        let mut save_slots = Persistent::<SaveSlots>::builder()
+            .name("Save Slots")
+            .format(StorageFormat::RonPrettyWithStructNames)
+            .path(save_slots_path.clone())
+            .default(SaveSlots::default())
+            .build()
+            .expect("failed to initialize save slots");
+        convert_legacy_building_types(&mut save_slots, &save_slots_path);
 
-        app.insert_resource(
-            Persistent::<SaveSlots>::builder()
-                .name("Save Slots")
-                .format(StorageFormat::RonPrettyWithStructNames)
-                .path(persistence_dir.join("save_slots.ron"))
-                .default(SaveSlots::default())
-                .build()
-                .expect("failed to initialize save slots"),
-        )
-        .add_event::<SaveToSaveSlot>()
-        .add_event::<LoadFromSaveSlot>()
-        .add_systems(Update, save_building_system)
-        .add_systems(Update, load_buildings_system)
-        .add_systems(Update, detect_save_and_load_key_press_system);
+        app.insert_resource(save_slots)
+            .init_resource::<BuildingLoadQueue>()
+            .add_event::<SaveToSaveSlot>()
+            .add_event::<LoadFromSaveSlot>()
+            .add_systems(
+                Update,
+                (
+                    save_building_system,
+                    load_buildings_system,
+                    detect_save_and_load_key_press_system,
+                )
+                    .run_if(in_state(AppState::Game)),
+            )
+            .add_systems(OnExit(AppState::Game), reset_load_queue_system);
     }
 }
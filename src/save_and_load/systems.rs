@@ -1,14 +1,17 @@
 use crate::building::building_components::*;
+use crate::building::building_definitions::BuildingDefinitions;
+use crate::building::building_history::BuildingHistory;
+use crate::player::player_components::{GameCamera, Inventory};
+use crate::research::research_components::Research;
 use crate::save_and_load::components::*;
 use crate::world_grid::world_gird_components::{
-    AsGridRotation, GridRotation, SurfaceLayer, WorldGrid,
+    AsGridRotation, GridRotation, ResourceNoiseSettings, SurfaceLayer, WorldGrid,
 };
 use bevy::input::keyboard::KeyboardInput;
 use bevy::prelude::*;
 use bevy::utils::info;
 use bevy_persistent::Persistent;
 use bevy_vector_shapes::prelude::*;
-use std::collections::VecDeque;
 
 pub fn detect_save_and_load_key_press_system(
     keys: Res<ButtonInput<KeyCode>>,
@@ -23,6 +26,7 @@ pub fn detect_save_and_load_key_press_system(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn load_buildings_system(
     mut load_event: EventReader<LoadFromSaveSlot>,
     mut save_slots: Res<Persistent<SaveSlots>>,
@@ -30,9 +34,14 @@ pub fn load_buildings_system(
     mut shapes: ShapeCommands,
     mut asset_server: ResMut<AssetServer>,
     mut world_grid: ResMut<WorldGrid>,
+    definitions: Res<BuildingDefinitions>,
     mut building_placed_event: EventWriter<BuildingPlacedEvent>,
+    mut history: ResMut<BuildingHistory>,
+    mut research: ResMut<Research>,
+    mut inventory: ResMut<Inventory>,
+    mut game_camera: ResMut<GameCamera>,
     //terrible hack, but for now it works (observers should be used later)
-    mut building_queue: Local<VecDeque<PlacedBuilding>>,
+    mut building_queue: ResMut<BuildingLoadQueue>,
 ) {
     let grid_size = world_grid.grid_size;
 
@@ -42,22 +51,37 @@ pub fn load_buildings_system(
             continue;
         };
         for building in &save.buildings {
-            building_queue.push_back(building.clone());
+            building_queue.0.push_back(building.clone());
         }
+        history.clear();
+        //deconstructing refunds the buildings, so the items have to come back with them
+        *inventory = save.inventory.clone();
+        game_camera.bookmarks = save.camera_bookmarks;
+        *research = Research {
+            unlocked: save.unlocked_techs.iter().cloned().collect(),
+            ..default()
+        };
     }
 
-    let Some(building) = building_queue.pop_front() else {
+    let Some(building) = building_queue.0.pop_front() else {
         return;
     };
 
     info!("placing building: {:?}", building);
+    let Some(definition) = definitions.get(&building.building_type) else {
+        warn!(
+            "unknown building {} in the save",
+            building.building_type.id()
+        );
+        return;
+    };
     let grid_position = world_grid.grid_position_from_world_position(building.position);
     let Some(cell) = world_grid.cells.get_mut(&grid_position) else {
         return;
     };
 
     let placed_building = Building::spawn(
-        building.building_type,
+        definition,
         building.position,
         building.rotation,
         grid_size,
@@ -65,17 +89,21 @@ pub fn load_buildings_system(
         &mut asset_server,
         &mut shapes,
     );
-    commands.entity(placed_building.unwrap()).insert(Active {});
+    if building.active {
+        commands.entity(placed_building).insert(Active {});
+    }
 
     cell.surface_layer = SurfaceLayer::Building {
-        entity: placed_building.unwrap(),
+        entity: placed_building,
     };
 
+    //loading a save is not something to undo
     building_placed_event.send(BuildingPlacedEvent {
-        entity: placed_building.unwrap(),
+        entity: placed_building,
         building_type: building.building_type,
         grid_position,
         grid_rotation: building.rotation.grid_rotation(),
+        from_history: true,
     });
 
     // if let Some(cell) = world_grid.cells.get_mut(&grid_position) {
@@ -118,29 +146,46 @@ pub fn load_buildings_system(
 pub fn save_building_system(
     mut events: EventReader<SaveToSaveSlot>,
     mut save_slots: ResMut<Persistent<SaveSlots>>,
-    q_buildings: Query<(&Building, &Transform)>,
+    resource_settings: Res<ResourceNoiseSettings>,
+    research: Res<Research>,
+    inventory: Res<Inventory>,
+    game_camera: Res<GameCamera>,
+    q_buildings: Query<(&Building, &Transform, Has<Active>)>,
 ) {
     for event in events.read() {
         save_slots
             .update(|save_slots| {
                 let mut placed_buildings = vec![];
-                for (building, transform) in &q_buildings {
+                for (building, transform, active) in &q_buildings {
                     placed_buildings.push(PlacedBuilding {
-                        building_type: building.building_type,
+                        building_type: building.building_type.clone(),
                         position: transform.translation,
                         rotation: transform.rotation,
                         size: transform.scale.x,
+                        active,
                     })
                 }
                 info!("saved buildings {:?}", placed_buildings);
+                let mut unlocked_techs = research.unlocked.iter().cloned().collect::<Vec<_>>();
+                unlocked_techs.sort();
 
                 save_slots.slots.insert(
                     event.slot_id,
                     SaveSlot {
                         buildings: placed_buildings,
+                        seed: resource_settings.seed,
+                        unlocked_techs,
+                        inventory: inventory.clone(),
+                        camera_bookmarks: game_camera.bookmarks,
                     },
                 );
+                save_slots.last_slot = Some(event.slot_id);
             })
             .expect("Updating Save Slots failed")
     }
 }
+
+///a save left half loaded must not keep placing buildings in the next game
+pub fn reset_load_queue_system(mut building_queue: ResMut<BuildingLoadQueue>) {
+    building_queue.0.clear();
+}
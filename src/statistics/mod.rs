@@ -0,0 +1,33 @@
+use crate::statistics::statistics_components::*;
+use crate::statistics::statistics_systems::*;
+use crate::AppState;
+use bevy::prelude::*;
+
+pub mod statistics_components;
+mod statistics_systems;
+
+pub struct StatisticsPlugin;
+
+impl Plugin for StatisticsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ItemProducedEvent>()
+            .add_event::<ItemConsumedEvent>()
+            .init_resource::<ProductionStatistics>()
+            .init_resource::<StatisticsPanel>()
+            .add_systems(OnEnter(AppState::Game), spawn_statistics_panel)
+            .add_systems(OnExit(AppState::Game), reset_statistics_system)
+            .add_systems(
+                Update,
+                (
+                    statistics_input_system,
+                    update_statistics_text_system.after(statistics_input_system),
+                    draw_statistics_graph_system.after(statistics_input_system),
+                )
+                    .run_if(in_state(AppState::Game)),
+            )
+            .add_systems(
+                PostUpdate,
+                record_statistics_system.run_if(in_state(AppState::Game)),
+            );
+    }
+}
@@ -0,0 +1,300 @@
+use crate::building::building_components::ItemType;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+///Every window keeps the same number of samples, longer windows use longer samples
+pub const SAMPLES_PER_WINDOW: usize = 60;
+
+#[derive(Event, Debug, Clone, Copy)]
+pub struct ItemProducedEvent {
+    pub item_type: ItemType,
+    pub amount: u32,
+}
+
+/// Items the player spends, on building costs and on research delivered to labs.
+/// Items lost with a removed belt or given back by deconstruction are not counted
+#[derive(Event, Debug, Clone, Copy)]
+pub struct ItemConsumedEvent {
+    pub item_type: ItemType,
+    pub amount: u32,
+}
+
+impl ItemConsumedEvent {
+    ///One event per item of a building cost
+    pub fn for_cost(cost: &[(ItemType, u32)]) -> Vec<ItemConsumedEvent> {
+        cost.iter()
+            .map(|(item_type, amount)| ItemConsumedEvent {
+                item_type: *item_type,
+                amount: *amount,
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum StatisticsWindow {
+    #[default]
+    OneMinute,
+    TenMinutes,
+    OneHour,
+}
+
+impl StatisticsWindow {
+    pub const ALL: [StatisticsWindow; 3] = [
+        StatisticsWindow::OneMinute,
+        StatisticsWindow::TenMinutes,
+        StatisticsWindow::OneHour,
+    ];
+
+    pub fn seconds(&self) -> f32 {
+        match self {
+            StatisticsWindow::OneMinute => 60.0,
+            StatisticsWindow::TenMinutes => 600.0,
+            StatisticsWindow::OneHour => 3600.0,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            StatisticsWindow::OneMinute => "1m",
+            StatisticsWindow::TenMinutes => "10m",
+            StatisticsWindow::OneHour => "1h",
+        }
+    }
+
+    fn index(&self) -> usize {
+        match self {
+            StatisticsWindow::OneMinute => 0,
+            StatisticsWindow::TenMinutes => 1,
+            StatisticsWindow::OneHour => 2,
+        }
+    }
+}
+
+/// Amounts per sample over one window, the oldest sample gets overwritten by the newest
+#[derive(Debug, Clone)]
+pub struct TimeSeries {
+    samples: Vec<f32>,
+    ///index of the oldest sample
+    head: usize,
+    ///completed samples, the series is full once it reaches the sample count
+    filled: usize,
+    sample_length: f32,
+    current: f32,
+    current_elapsed: f32,
+}
+
+impl TimeSeries {
+    pub fn new(window: StatisticsWindow) -> Self {
+        Self {
+            samples: vec![0.0; SAMPLES_PER_WINDOW],
+            head: 0,
+            filled: 0,
+            sample_length: window.seconds() / SAMPLES_PER_WINDOW as f32,
+            current: 0.0,
+            current_elapsed: 0.0,
+        }
+    }
+
+    pub fn record(&mut self, amount: f32) {
+        self.current += amount;
+    }
+
+    pub fn advance(&mut self, delta: f32) {
+        self.current_elapsed += delta;
+        while self.current_elapsed >= self.sample_length {
+            self.current_elapsed -= self.sample_length;
+            self.samples[self.head] = std::mem::take(&mut self.current);
+            self.head = (self.head + 1) % self.samples.len();
+            self.filled = (self.filled + 1).min(self.samples.len());
+        }
+    }
+
+    ///Completed samples from the oldest to the newest
+    pub fn samples(&self) -> impl Iterator<Item = f32> + '_ {
+        let len = self.samples.len();
+        (len - self.filled..len).map(move |i| self.samples[(self.head + i) % len])
+    }
+
+    pub fn sample_length(&self) -> f32 {
+        self.sample_length
+    }
+
+    ///Average amount per minute over the part of the window that was recorded so far
+    pub fn rate_per_minute(&self) -> f32 {
+        let recorded_time = self.filled as f32 * self.sample_length + self.current_elapsed;
+        if recorded_time <= 0.0 {
+            return 0.0;
+        }
+        let total = self.samples().sum::<f32>() + self.current;
+        total / recorded_time * 60.0
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ItemStatistics {
+    produced: [TimeSeries; 3],
+    consumed: [TimeSeries; 3],
+}
+
+impl Default for ItemStatistics {
+    fn default() -> Self {
+        let series = StatisticsWindow::ALL.map(TimeSeries::new);
+        Self {
+            produced: series.clone(),
+            consumed: series,
+        }
+    }
+}
+
+impl ItemStatistics {
+    pub fn produced(&self, window: StatisticsWindow) -> &TimeSeries {
+        &self.produced[window.index()]
+    }
+
+    pub fn consumed(&self, window: StatisticsWindow) -> &TimeSeries {
+        &self.consumed[window.index()]
+    }
+}
+
+/// Produced and consumed items per type, measured in simulation time
+#[derive(Resource, Default, Debug)]
+pub struct ProductionStatistics {
+    pub items: HashMap<ItemType, ItemStatistics>,
+}
+
+impl ProductionStatistics {
+    pub fn record_produced(&mut self, item_type: ItemType, amount: u32) {
+        let statistics = self.items.entry(item_type).or_default();
+        for series in statistics.produced.iter_mut() {
+            series.record(amount as f32);
+        }
+    }
+
+    pub fn record_consumed(&mut self, item_type: ItemType, amount: u32) {
+        let statistics = self.items.entry(item_type).or_default();
+        for series in statistics.consumed.iter_mut() {
+            series.record(amount as f32);
+        }
+    }
+
+    pub fn advance(&mut self, delta: f32) {
+        for statistics in self.items.values_mut() {
+            for series in statistics
+                .produced
+                .iter_mut()
+                .chain(statistics.consumed.iter_mut())
+            {
+                series.advance(delta);
+            }
+        }
+    }
+
+    ///Item types sorted by name, so the panel and the export keep their order
+    pub fn sorted_items(&self) -> Vec<(&ItemType, &ItemStatistics)> {
+        let mut items = self.items.iter().collect::<Vec<_>>();
+        items.sort_by_key(|(item_type, _)| format!("{:?}", item_type));
+        items
+    }
+
+    ///All samples as `item,window,seconds_ago,produced,consumed` lines
+    pub fn to_csv(&self) -> String {
+        let mut csv = "item,window,seconds_ago,produced,consumed\n".to_string();
+        for (item_type, statistics) in self.sorted_items() {
+            for window in StatisticsWindow::ALL {
+                let produced = statistics.produced(window);
+                let consumed = statistics.consumed(window).samples().collect::<Vec<_>>();
+                let samples = produced.samples().collect::<Vec<_>>();
+                for (i, amount) in samples.iter().enumerate() {
+                    let seconds_ago = (samples.len() - i) as f32 * produced.sample_length();
+                    csv.push_str(&format!(
+                        "{:?},{},{},{},{}\n",
+                        item_type,
+                        window.label(),
+                        seconds_ago,
+                        amount,
+                        consumed.get(i).copied().unwrap_or_default()
+                    ));
+                }
+            }
+        }
+        csv
+    }
+}
+
+/// The statistics panel, toggled with Tab
+#[derive(Resource, Default, Debug)]
+pub struct StatisticsPanel {
+    pub visible: bool,
+    pub window: StatisticsWindow,
+    pub canvas: Option<Entity>,
+}
+
+#[derive(Component)]
+pub struct StatisticsPanelRoot;
+
+#[derive(Component)]
+pub struct StatisticsRatesText;
+
+#[derive(Component, Debug, Clone, Copy)]
+pub enum StatisticsButton {
+    Window(StatisticsWindow),
+    ExportCsv,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn one_minute_series() -> TimeSeries {
+        //one second per sample
+        TimeSeries::new(StatisticsWindow::OneMinute)
+    }
+
+    #[test]
+    fn samples_are_returned_from_the_oldest_to_the_newest() {
+        let mut series = one_minute_series();
+        for amount in 1..=3 {
+            series.record(amount as f32);
+            series.advance(1.0);
+        }
+        assert_eq!(series.samples().collect::<Vec<_>>(), vec![1.0, 2.0, 3.0]);
+    }
+
+    #[test]
+    fn a_full_series_overwrites_the_oldest_sample() {
+        let mut series = one_minute_series();
+        for amount in 0..SAMPLES_PER_WINDOW + 2 {
+            series.record(amount as f32);
+            series.advance(1.0);
+        }
+        let samples = series.samples().collect::<Vec<_>>();
+        assert_eq!(samples.len(), SAMPLES_PER_WINDOW);
+        assert_eq!(samples.first(), Some(&2.0));
+        assert_eq!(samples.last(), Some(&((SAMPLES_PER_WINDOW + 1) as f32)));
+    }
+
+    #[test]
+    fn a_long_frame_completes_several_samples() {
+        let mut series = one_minute_series();
+        series.record(4.0);
+        series.advance(2.5);
+        assert_eq!(series.samples().collect::<Vec<_>>(), vec![4.0, 0.0]);
+    }
+
+    #[test]
+    fn rate_covers_only_the_recorded_part_of_the_window() {
+        let mut series = one_minute_series();
+        assert_eq!(series.rate_per_minute(), 0.0);
+        //10 items in the first 10 seconds are 60 per minute, not 10
+        for _ in 0..10 {
+            series.record(1.0);
+            series.advance(1.0);
+        }
+        assert!((series.rate_per_minute() - 60.0).abs() < 1e-3);
+        //the unfinished sample counts as well
+        series.record(1.0);
+        series.advance(0.5);
+        assert!((series.rate_per_minute() - 11.0 / 10.5 * 60.0).abs() < 1e-3);
+    }
+}
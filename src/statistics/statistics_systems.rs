@@ -0,0 +1,258 @@
+use crate::statistics::statistics_components::*;
+use crate::{AppState, MainCamera};
+use bevy::color::palettes::css::RED;
+use bevy::prelude::*;
+use bevy_vector_shapes::prelude::*;
+use std::path::PathBuf;
+
+const GRAPH_WIDTH: u32 = 240;
+const GRAPH_HEIGHT: u32 = 120;
+
+fn statistics_csv_path() -> PathBuf {
+    std::env::current_dir()
+        .unwrap()
+        .join("assets")
+        .join("statistics.csv")
+}
+
+pub fn spawn_statistics_panel(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    camera_query: Query<Entity, With<MainCamera>>,
+    asset_server: Res<AssetServer>,
+    mut panel: ResMut<StatisticsPanel>,
+) {
+    let main_camera = camera_query.get_single().unwrap();
+    let font = asset_server.load("fonts/FiraMono-Medium.ttf");
+
+    //the graph is painted into a canvas image that the panel shows
+    let (graph_image, canvas) = commands.spawn_canvas(
+        images.as_mut(),
+        CanvasConfig::new(GRAPH_WIDTH, GRAPH_HEIGHT),
+    );
+    commands.entity(canvas).insert(StateScoped(AppState::Game));
+    panel.canvas = Some(canvas);
+
+    let visibility = if panel.visible {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
+    };
+    commands
+        .spawn((
+            TargetCamera(main_camera),
+            Node {
+                position_type: PositionType::Absolute,
+                top: Val::Px(40.0),
+                right: Val::Px(5.0),
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(4.0),
+                padding: UiRect::all(Val::Px(5.0)),
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.1, 0.1, 0.1, 0.9)),
+            BorderRadius::all(Val::Px(5.0)),
+            visibility,
+            StatisticsPanelRoot,
+            StateScoped(AppState::Game),
+            Name::new("Statistics Panel"),
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn(Node {
+                    column_gap: Val::Px(4.0),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for window in StatisticsWindow::ALL {
+                        statistics_button(
+                            parent,
+                            font.clone(),
+                            window.label(),
+                            StatisticsButton::Window(window),
+                        );
+                    }
+                    statistics_button(parent, font.clone(), "CSV", StatisticsButton::ExportCsv);
+                });
+            parent.spawn((
+                Text::default(),
+                TextFont {
+                    font: font.clone(),
+                    font_size: 10.0,
+                    ..default()
+                },
+                StatisticsRatesText,
+            ));
+            parent.spawn((
+                Node {
+                    width: Val::Px(GRAPH_WIDTH as f32),
+                    height: Val::Px(GRAPH_HEIGHT as f32),
+                    ..default()
+                },
+                ImageNode::new(graph_image),
+            ));
+        });
+}
+
+fn statistics_button(
+    parent: &mut ChildBuilder,
+    font: Handle<Font>,
+    text: &str,
+    button: StatisticsButton,
+) {
+    parent
+        .spawn((
+            Button,
+            Node {
+                width: Val::Px(40.0),
+                height: Val::Px(20.0),
+                border: UiRect::all(Val::Px(1.0)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            BorderColor(Color::BLACK),
+            BackgroundColor(Color::srgb(0.15, 0.15, 0.15)),
+            button,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text(text.to_owned()),
+                TextFont {
+                    font,
+                    font_size: 10.0,
+                    ..default()
+                },
+            ));
+        });
+}
+
+pub fn record_statistics_system(
+    time: Res<Time>,
+    mut statistics: ResMut<ProductionStatistics>,
+    mut produced_events: EventReader<ItemProducedEvent>,
+    mut consumed_events: EventReader<ItemConsumedEvent>,
+) {
+    for event in produced_events.read() {
+        statistics.record_produced(event.item_type, event.amount);
+    }
+    for event in consumed_events.read() {
+        statistics.record_consumed(event.item_type, event.amount);
+    }
+    //simulation time, a paused game does not dilute the rates
+    statistics.advance(time.delta_secs());
+}
+
+pub fn statistics_input_system(
+    keys: Res<ButtonInput<KeyCode>>,
+    interaction_query: Query<(&Interaction, &StatisticsButton), Changed<Interaction>>,
+    statistics: Res<ProductionStatistics>,
+    mut panel: ResMut<StatisticsPanel>,
+    mut panel_q: Query<&mut Visibility, With<StatisticsPanelRoot>>,
+) {
+    if keys.just_pressed(KeyCode::Tab) {
+        panel.visible = !panel.visible;
+        for mut visibility in panel_q.iter_mut() {
+            *visibility = if panel.visible {
+                Visibility::Inherited
+            } else {
+                Visibility::Hidden
+            };
+        }
+    }
+    for (interaction, button) in interaction_query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        match button {
+            StatisticsButton::Window(window) => panel.window = *window,
+            StatisticsButton::ExportCsv => {
+                match std::fs::write(statistics_csv_path(), statistics.to_csv()) {
+                    Ok(()) => info!("exported the production statistics"),
+                    Err(e) => error!("could not write the statistics {:?}", e),
+                }
+            }
+        }
+    }
+}
+
+pub fn update_statistics_text_system(
+    statistics: Res<ProductionStatistics>,
+    panel: Res<StatisticsPanel>,
+    mut text_q: Query<&mut Text, With<StatisticsRatesText>>,
+) {
+    if !panel.visible {
+        return;
+    }
+    let Ok(mut text) = text_q.get_single_mut() else {
+        return;
+    };
+    let mut lines = vec![
+        format!("Items per minute, last {}", panel.window.label()),
+        "+mined and extracted -spent on buildings and research".to_string(),
+    ];
+    for (item_type, item_statistics) in statistics.sorted_items() {
+        lines.push(format!(
+            "{:?}: +{:.1} -{:.1}",
+            item_type,
+            item_statistics.produced(panel.window).rate_per_minute(),
+            item_statistics.consumed(panel.window).rate_per_minute()
+        ));
+    }
+    text.0 = lines.join("\n");
+}
+
+pub fn draw_statistics_graph_system(
+    mut painter: ShapePainter,
+    statistics: Res<ProductionStatistics>,
+    panel: Res<StatisticsPanel>,
+) {
+    let Some(canvas) = panel.canvas.filter(|_| panel.visible) else {
+        return;
+    };
+    painter.set_canvas(canvas);
+    painter.thickness = 1.5;
+
+    let window = panel.window;
+    let series = statistics
+        .sorted_items()
+        .into_iter()
+        .flat_map(|(item_type, item_statistics)| {
+            [
+                (item_type.color(), item_statistics.produced(window)),
+                (RED.into(), item_statistics.consumed(window)),
+            ]
+        })
+        .collect::<Vec<(Color, &TimeSeries)>>();
+    let highest = series
+        .iter()
+        .flat_map(|(_, series)| series.samples())
+        .fold(1.0_f32, f32::max);
+
+    //the canvas is centred on the origin, newest samples end on the right edge
+    let width = GRAPH_WIDTH as f32;
+    let height = GRAPH_HEIGHT as f32;
+    let point = |index: usize, count: usize, amount: f32| {
+        let x = width * 0.5 - (count - 1 - index) as f32 / (SAMPLES_PER_WINDOW - 1) as f32 * width;
+        let y = -height * 0.5 + amount / highest * height * 0.9;
+        Vec3::new(x, y, 0.0)
+    };
+    for (color, series) in series {
+        let samples = series.samples().collect::<Vec<_>>();
+        painter.color = color;
+        for (i, pair) in samples.windows(2).enumerate() {
+            painter.line(
+                point(i, samples.len(), pair[0]),
+                point(i + 1, samples.len(), pair[1]),
+            );
+        }
+    }
+}
+
+pub fn reset_statistics_system(
+    mut statistics: ResMut<ProductionStatistics>,
+    mut panel: ResMut<StatisticsPanel>,
+) {
+    *statistics = ProductionStatistics::default();
+    *panel = StatisticsPanel::default();
+}
@@ -1,5 +1,10 @@
+use crate::building::building_components::BuildingType;
 use bevy::ecs::world::Command;
 use bevy::prelude::*;
+use bevy_persistent::Persistent;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::path::Path;
 
 pub struct CloneEntity {
     pub source: Entity,
@@ -65,6 +70,28 @@ impl CloneEntity {
     }
 }
 
+///Files from before the definition files name the buildings by their old enum. Those names load
+///as no building, so the text of the file is converted and read again, the file itself keeps its
+///content until the game saves it
+pub fn convert_legacy_building_types<R>(persistent: &mut Persistent<R>, path: &Path)
+where
+    R: Resource + Serialize + DeserializeOwned,
+{
+    let Ok(text) = std::fs::read_to_string(path) else {
+        return;
+    };
+    let Some(converted) = BuildingType::migrate_legacy_ron(&text) else {
+        return;
+    };
+    match ron::de::from_str::<R>(&converted) {
+        Ok(resource) => {
+            *persistent.get_mut() = resource;
+            info!("converted the building types of {:?}", path);
+        }
+        Err(e) => error!("could not convert the building types of {:?}: {}", path, e),
+    }
+}
+
 pub fn find_child_with_name(
     entity: Entity,
     name: &str,
@@ -1,34 +1,16 @@
+use crate::building::building_components::ItemType;
+use crate::world_grid::world_gird_components::{GridPosition, GroundLayerType, WorldGrid};
+use crate::AppState;
+use avian3d::prelude::{Collider, RigidBody};
 use bevy::asset::AssetServer;
-use bevy::color::palettes::css::YELLOW;
 use bevy::math::{Quat, Vec3};
 use bevy::prelude::*;
-use bevy_vector_shapes::painter::ShapeCommands;
-use bevy_vector_shapes::prelude::*;
-use std::f32::consts::TAU;
 
 #[derive(Component, Default)]
-#[allow(dead_code)]
 pub struct YellowBileResource {
     pub amount: i32,
 }
 
-#[derive(Component, Default)]
-pub struct YellowBileItem {}
-
-impl YellowBileItem {
-    pub fn spawn(position: Vec3, rotation: Quat, shapes: &mut ShapeCommands) -> Entity {
-        shapes.reset = true;
-        shapes.transform = Transform::from_translation(position + Vec3::Y * 0.2);
-        shapes.color = YELLOW.into();
-        shapes.rotate_x(TAU * 0.25);
-        shapes.rotate(rotation);
-        shapes
-            .circle(0.05)
-            .insert((YellowBileItem {}, Name::new("YellowItem")))
-            .id()
-    }
-}
-
 impl YellowBileResource {
     pub fn spawn(
         position: Vec3,
@@ -46,7 +28,33 @@ impl YellowBileResource {
                     .with_rotation(rotation)
                     .with_scale(Vec3::splat(size)),
                 YellowBileResource { amount },
+                RigidBody::Static,
+                Collider::cuboid(0.8, 2.0, 0.8),
+                StateScoped(AppState::Game),
             ))
             .id()
     }
 }
+
+///Takes one item out of the resource node on the cell, a used up node disappears from the world
+pub fn mine_resource_node(
+    commands: &mut Commands,
+    world_grid: &mut WorldGrid,
+    node_q: &mut Query<&mut YellowBileResource>,
+    grid_position: &GridPosition,
+) -> Option<ItemType> {
+    let cell = world_grid.cells.get_mut(grid_position)?;
+    let node_entity = cell.resource_node?;
+    let mut node = node_q.get_mut(node_entity).ok()?;
+    if node.amount <= 0 {
+        return None;
+    }
+    node.amount -= 1;
+    if node.amount <= 0 {
+        info!("resource node at {:?} is depleted", grid_position);
+        commands.entity(node_entity).despawn_recursive();
+        cell.resource_node = None;
+        cell.ground_layer = GroundLayerType::Empty;
+    }
+    Some(ItemType::YellowBile)
+}
@@ -1,30 +1,35 @@
-use bevy::prelude::*;
-use crate::world_grid::world_gird_components::{Cell, GridPosition, ResourceNoiseSettings, WorldGrid};
+use crate::world_grid::world_gird_components::{
+    Cell, GridPosition, ResourceNoiseSettings, WorldGrid,
+};
 use crate::world_grid::world_grid_systems::*;
+use crate::AppState;
+use bevy::prelude::*;
 
+pub mod components;
 pub mod world_gird_components;
 mod world_grid_systems;
-pub mod components;
 
 pub struct WorldGridPlugin;
 
 impl Plugin for WorldGridPlugin {
     fn build(&self, app: &mut App) {
-        app
-            .register_type::<GridPosition>()
+        app.register_type::<GridPosition>()
             .register_type::<Cell>()
             .register_type::<WorldGrid>()
-            .insert_resource(WorldGrid::new( 0.5))
+            .insert_resource(WorldGrid::new(0.5))
             .insert_resource(ResourceNoiseSettings {
                 zoom_level: 0.02,
                 // bile_level: 0.83,
                 bile_level: 0.65,
+                seed: 0,
             })
             // .add_systems(Startup, debug_world_system)
             // .add_systems(Startup, debug_spawn_grid_positions)
             // .add_systems(Startup, gird_test_system.before(debug_spawn_grid_positions))
-            .add_systems(Update, draw_grid)
-            .add_systems(Update, discover_world_system)
-        ;
+            .add_systems(
+                Update,
+                (draw_grid, discover_world_system).run_if(in_state(AppState::Game)),
+            )
+            .add_systems(OnExit(AppState::Game), reset_world_grid_system);
     }
-}
\ No newline at end of file
+}
@@ -1,13 +1,30 @@
 use bevy::{prelude::*, utils::HashMap};
+use serde::{Deserialize, Serialize};
 use std::{f32::consts::PI, f32::consts::TAU, ops};
 
-#[derive(Component, Reflect, Hash, Eq, PartialEq, Debug, Clone, Default, Copy)]
+#[derive(
+    Component, Reflect, Hash, Eq, PartialEq, Debug, Clone, Default, Copy, Serialize, Deserialize,
+)]
 pub struct GridPosition {
     pub x: i32,
     pub y: i32,
 }
 
 impl GridPosition {
+    ///Lowest and highest corner of the region spanned by two grid positions
+    pub fn region(a: GridPosition, b: GridPosition) -> (GridPosition, GridPosition) {
+        (
+            GridPosition {
+                x: a.x.min(b.x),
+                y: a.y.min(b.y),
+            },
+            GridPosition {
+                x: a.x.max(b.x),
+                y: a.y.max(b.y),
+            },
+        )
+    }
+
     #[allow(dead_code)]
     pub fn get_neighbour(&self, direction: GridRotation) -> GridPosition {
         match direction {
@@ -137,7 +154,7 @@ impl ops::Add<GridPosition> for GridPosition {
     }
 }
 
-#[derive(Hash, Eq, PartialEq, Default, Clone, Reflect, Debug)]
+#[derive(Hash, Eq, PartialEq, Default, Clone, Reflect, Debug, Serialize, Deserialize)]
 pub enum GroundLayerType {
     #[default]
     Empty,
@@ -147,7 +164,7 @@ pub enum GroundLayerType {
     PhlegmResource,
 }
 
-#[derive(Reflect, Default, Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Reflect, Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum GridRotation {
     #[default]
     N,
@@ -199,6 +216,36 @@ impl GridRotation {
         }
     }
 
+    ///Rotation that points from one grid position to its direct neighbour
+    pub fn towards(from: GridPosition, to: GridPosition) -> Option<GridRotation> {
+        match (to.x - from.x, to.y - from.y) {
+            (0, 1) => Some(GridRotation::N),
+            (0, -1) => Some(GridRotation::S),
+            (1, 0) => Some(GridRotation::W),
+            (-1, 0) => Some(GridRotation::E),
+            _ => None,
+        }
+    }
+
+    ///Turns a quarter the same way R turns the building preview
+    pub fn rotated_quarter(&self) -> GridRotation {
+        match self {
+            GridRotation::N => GridRotation::E,
+            GridRotation::E => GridRotation::S,
+            GridRotation::S => GridRotation::W,
+            GridRotation::W => GridRotation::N,
+        }
+    }
+
+    pub fn to_quat(&self) -> Quat {
+        match self {
+            GridRotation::N => Quat::IDENTITY,
+            GridRotation::W => Quat::from_rotation_y(TAU * 0.25),
+            GridRotation::S => Quat::from_rotation_y(TAU * 0.5),
+            GridRotation::E => Quat::from_rotation_y(TAU * 0.75),
+        }
+    }
+
     pub fn get_direction(&self) -> Dir3 {
         match self {
             GridRotation::N => Dir3::Z,
@@ -279,6 +326,8 @@ pub struct Cell {
     pub ground_layer: GroundLayerType,
     pub surface_layer: SurfaceLayer,
     pub item_layer: ItemLayer,
+    ///resource node entity that is mined on this cell
+    pub resource_node: Option<Entity>,
 }
 
 #[derive(Resource, Reflect, Default)]
@@ -286,6 +335,8 @@ pub struct Cell {
 pub struct ResourceNoiseSettings {
     pub zoom_level: f32,
     pub bile_level: f32,
+    ///seed of the world, the same seed places the resources at the same spots
+    pub seed: u32,
 }
 
 #[derive(Resource, Reflect, Default)]
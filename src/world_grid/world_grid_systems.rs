@@ -1,7 +1,9 @@
+use crate::main_menu::main_menu_components::GameSettings;
 use crate::player::player_components::{GameCursor, Player};
 use crate::world_grid::components::yellow_bile::YellowBileResource;
 use crate::world_grid::world_gird_components::*;
 use bevy::prelude::*;
+use bevy_persistent::Persistent;
 use bevy_vector_shapes::prelude::*;
 use noisy_bevy::simplex_noise_2d_seeded;
 use std::f32::consts::TAU;
@@ -11,9 +13,10 @@ pub fn draw_grid(
     mut painter: ShapePainter,
     world_grid: Res<WorldGrid>,
     game_cursor: Res<GameCursor>,
+    settings: Res<Persistent<GameSettings>>,
     player_q: Query<&Transform, With<Player>>,
 ) {
-    if player_q.get_single().is_err() {
+    if player_q.get_single().is_err() || !settings.show_grid {
         return;
     }
     let rotation = Quat::from_rotation_x(TAU * 0.25);
@@ -60,15 +63,17 @@ pub fn draw_grid(
     }
 }
 
-fn get_noise_value(grid_position: GridPosition, zoom_level: f32) -> f32 {
+fn get_noise_value(grid_position: GridPosition, zoom_level: f32, seed: u32) -> f32 {
     let x = grid_position.x as f32;
     let y = grid_position.y as f32;
     let frequencies = vec![1.0, 0.5, 0.25];
     let mut combined_noise = 0.0;
     for f in &frequencies {
         combined_noise += f
-            * ((simplex_noise_2d_seeded(Vec2::new(f * x * zoom_level, f * y * zoom_level), *f)
-                + 1.0)
+            * ((simplex_noise_2d_seeded(
+                Vec2::new(f * x * zoom_level, f * y * zoom_level),
+                *f + seed as f32,
+            ) + 1.0)
                 * 0.5)
     }
     combined_noise / frequencies.iter().sum::<f32>()
@@ -98,11 +103,14 @@ pub fn discover_world_system(
             let grid_position = GridPosition { x, y };
 
             if world_grid.cells.get(&grid_position).is_none() {
-                if get_noise_value(grid_position, resource_settings.zoom_level)
-                    > resource_settings.bile_level
+                if get_noise_value(
+                    grid_position,
+                    resource_settings.zoom_level,
+                    resource_settings.seed,
+                ) > resource_settings.bile_level
                 {
                     let position = world_grid.grid_to_world(&grid_position);
-                    YellowBileResource::spawn(
+                    let resource_node = YellowBileResource::spawn(
                         position,
                         Quat::default(),
                         world_grid.grid_size,
@@ -116,6 +124,7 @@ pub fn discover_world_system(
                             ground_layer: GroundLayerType::YellowBileResource,
                             surface_layer: SurfaceLayer::Empty,
                             item_layer: ItemLayer::Empty,
+                            resource_node: Some(resource_node),
                         },
                     );
                 } else {
@@ -125,6 +134,7 @@ pub fn discover_world_system(
                             ground_layer: GroundLayerType::Empty,
                             surface_layer: SurfaceLayer::Empty,
                             item_layer: ItemLayer::Empty,
+                            resource_node: None,
                         },
                     );
                 }
@@ -132,3 +142,8 @@ pub fn discover_world_system(
         }
     }
 }
+
+pub fn reset_world_grid_system(mut world_grid: ResMut<WorldGrid>) {
+    info!("leaving the game, clearing the world");
+    world_grid.cells.clear();
+}